@@ -0,0 +1,158 @@
+//! Grouping of captured packets into flows by their IP/TCP/UDP 5-tuple.
+//!
+//! This module is only available with the `flow` feature.
+
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use super::parse::{ETHERTYPE_IPV4, ETHERTYPE_IPV6};
+use super::{Activated, Capture, Error, Linktype, Packet, PacketOwned};
+
+const IPPROTO_TCP: u8 = 6;
+const IPPROTO_UDP: u8 = 17;
+
+/// The 5-tuple identifying the flow a packet belongs to. Ports are zero for protocols other
+/// than TCP and UDP, and for fragments which do not carry the transport header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FlowKey {
+    pub src: IpAddr,
+    pub dst: IpAddr,
+    pub src_port: u16,
+    pub dst_port: u16,
+    pub protocol: u8,
+}
+
+impl FlowKey {
+    /// Parses the flow key of a packet with the given link type. Returns `None` if the packet
+    /// is not an IPv4 or IPv6 packet, or its headers are truncated.
+    pub fn from_packet(packet: &Packet, linktype: Linktype) -> Option<FlowKey> {
+        let (ethertype, data) = packet.network_layer(linktype)?;
+        let (src, dst, protocol, transport) = match ethertype {
+            ETHERTYPE_IPV4 => parse_ipv4(data)?,
+            ETHERTYPE_IPV6 => parse_ipv6(data)?,
+            _ => return None,
+        };
+        let (src_port, dst_port) = match (protocol, transport) {
+            (IPPROTO_TCP, Some(t)) | (IPPROTO_UDP, Some(t)) if t.len() >= 4 => {
+                (u16::from_be_bytes([t[0], t[1]]), u16::from_be_bytes([t[2], t[3]]))
+            }
+            (IPPROTO_TCP, Some(_)) | (IPPROTO_UDP, Some(_)) => return None,
+            _ => (0, 0),
+        };
+        Some(FlowKey { src, dst, src_port, dst_port, protocol })
+    }
+}
+
+/// Returns the addresses, protocol and transport-layer data of an IPv4 packet. The transport
+/// data is `None` for fragments other than the first.
+fn parse_ipv4(data: &[u8]) -> Option<(IpAddr, IpAddr, u8, Option<&[u8]>)> {
+    if data.len() < 20 || data[0] >> 4 != 4 {
+        return None;
+    }
+    let header_len = ((data[0] & 0x0f) as usize) * 4;
+    if header_len < 20 {
+        return None;
+    }
+    let fragment_offset = u16::from_be_bytes([data[6], data[7]]) & 0x1fff;
+    let src = Ipv4Addr::new(data[12], data[13], data[14], data[15]);
+    let dst = Ipv4Addr::new(data[16], data[17], data[18], data[19]);
+    let transport = if fragment_offset == 0 { Some(data.get(header_len..)?) } else { None };
+    Some((src.into(), dst.into(), data[9], transport))
+}
+
+/// Returns the addresses, protocol and transport-layer data of an IPv6 packet, skipping any
+/// extension headers. The transport data is `None` for fragments other than the first.
+fn parse_ipv6(data: &[u8]) -> Option<(IpAddr, IpAddr, u8, Option<&[u8]>)> {
+    if data.len() < 40 || data[0] >> 4 != 6 {
+        return None;
+    }
+    let mut src = [0u8; 16];
+    let mut dst = [0u8; 16];
+    src.copy_from_slice(&data[8..24]);
+    dst.copy_from_slice(&data[24..40]);
+
+    let mut next_header = data[6];
+    let mut rest = &data[40..];
+    let mut first_fragment = true;
+    loop {
+        match next_header {
+            // Hop-by-hop options, routing and destination options
+            0 | 43 | 60 => {
+                let len = (*rest.get(1)? as usize + 1) * 8;
+                next_header = rest[0];
+                rest = rest.get(len..)?;
+            }
+            // Fragment
+            44 => {
+                let header = rest.get(..8)?;
+                first_fragment = u16::from_be_bytes([header[2], header[3]]) >> 3 == 0;
+                next_header = header[0];
+                rest = &rest[8..];
+            }
+            _ => break,
+        }
+    }
+    let transport = if first_fragment { Some(rest) } else { None };
+    Some((Ipv6Addr::from(src).into(), Ipv6Addr::from(dst).into(), next_header, transport))
+}
+
+/// Reads packets from a capture and pairs each IP packet with the key of the flow it belongs
+/// to. Packets which are not IP packets are skipped.
+///
+/// Iteration stops when the capture returns `NoMorePackets`; other errors (including
+/// `TimeoutExpired` on live captures) are yielded, and iteration may continue afterwards.
+///
+/// # Example
+///
+/// ```ignore
+/// use pcap::flow::FlowReader;
+///
+/// let cap = pcap::Capture::from_file("capture.pcap").unwrap();
+/// for (key, packets) in FlowReader::new(cap).into_flows().unwrap() {
+///     println!("{:?}: {} packets", key, packets.len());
+/// }
+/// ```
+pub struct FlowReader<T: Activated + ?Sized> {
+    cap: Capture<T>,
+    linktype: Linktype,
+}
+
+impl<T: Activated + ?Sized> FlowReader<T> {
+    pub fn new(cap: Capture<T>) -> FlowReader<T> {
+        let linktype = cap.get_datalink();
+        FlowReader { cap, linktype }
+    }
+
+    /// Returns the underlying capture handle.
+    pub fn into_inner(self) -> Capture<T> {
+        self.cap
+    }
+
+    /// Reads all remaining packets, grouped by flow. Stops at the first error other than
+    /// `NoMorePackets`.
+    pub fn into_flows(self) -> Result<HashMap<FlowKey, Vec<PacketOwned>>, Error> {
+        let mut flows: HashMap<FlowKey, Vec<PacketOwned>> = HashMap::new();
+        for item in self {
+            let (key, packet) = item?;
+            flows.entry(key).or_default().push(packet);
+        }
+        Ok(flows)
+    }
+}
+
+impl<T: Activated + ?Sized> Iterator for FlowReader<T> {
+    type Item = Result<(FlowKey, PacketOwned), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let packet = match self.cap.next() {
+                Ok(packet) => packet,
+                Err(Error::NoMorePackets) => return None,
+                Err(e) => return Some(Err(e)),
+            };
+            if let Some(key) = FlowKey::from_packet(&packet, self.linktype) {
+                return Some(Ok((key, packet.into())));
+            }
+        }
+    }
+}
@@ -0,0 +1,63 @@
+//! Building of link-layer frames for sending with `Capture::sendpacket`.
+
+/// The minimum length of an Ethernet frame, excluding the frame check sequence which the
+/// NIC appends.
+pub const ETHERNET_MIN_LEN: usize = 60;
+
+/// Builds an Ethernet II frame from its addresses, EtherType and payload.
+///
+/// By default, frames shorter than `ETHERNET_MIN_LEN` are padded with zeros. Most NICs pad
+/// short frames themselves, but some drivers send them unpadded, which receivers drop as runts.
+///
+/// # Example
+///
+/// ```ignore
+/// use pcap::frame::EthernetFrameBuilder;
+///
+/// let frame = EthernetFrameBuilder::new([0xff; 6], [0x02, 0, 0, 0, 0, 1], 0x88b5)
+///     .payload(b"hello")
+///     .build();
+/// cap.sendpacket(frame).unwrap();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EthernetFrameBuilder {
+    dst: [u8; 6],
+    src: [u8; 6],
+    ethertype: u16,
+    payload: Vec<u8>,
+    pad: bool,
+}
+
+impl EthernetFrameBuilder {
+    /// Creates a builder for a frame from `src` to `dst` carrying `ethertype`, with an empty
+    /// payload.
+    pub fn new(dst: [u8; 6], src: [u8; 6], ethertype: u16) -> EthernetFrameBuilder {
+        EthernetFrameBuilder { dst, src, ethertype, payload: vec![], pad: true }
+    }
+
+    /// Sets the payload following the Ethernet header.
+    pub fn payload<P: Into<Vec<u8>>>(mut self, payload: P) -> EthernetFrameBuilder {
+        self.payload = payload.into();
+        self
+    }
+
+    /// Sets whether frames shorter than `ETHERNET_MIN_LEN` are padded with zeros. Enabled by
+    /// default.
+    pub fn pad(mut self, pad: bool) -> EthernetFrameBuilder {
+        self.pad = pad;
+        self
+    }
+
+    /// Returns the frame's bytes.
+    pub fn build(self) -> Vec<u8> {
+        let mut frame = Vec::with_capacity(ETHERNET_MIN_LEN.max(14 + self.payload.len()));
+        frame.extend_from_slice(&self.dst);
+        frame.extend_from_slice(&self.src);
+        frame.extend_from_slice(&self.ethertype.to_be_bytes());
+        frame.extend_from_slice(&self.payload);
+        if self.pad && frame.len() < ETHERNET_MIN_LEN {
+            frame.resize(ETHERNET_MIN_LEN, 0);
+        }
+        frame
+    }
+}
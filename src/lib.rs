@@ -49,25 +49,91 @@
 use unique::Unique;
 
 use std::borrow::Borrow;
+use std::collections::HashMap;
+#[cfg(not(windows))]
+use std::convert::TryFrom;
 use std::marker::PhantomData;
 use std::ptr;
 use std::ffi::{self, CString, CStr};
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicI32, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
 use std::slice;
-use std::ops::Deref;
+use std::ops::{Deref, DerefMut, Range};
 use std::mem;
 use std::fmt;
-#[cfg(feature = "capture-stream")]
+#[cfg(not(windows))]
+use std::time::Duration;
 use std::io;
 #[cfg(not(windows))]
 use std::os::unix::io::{RawFd, AsRawFd};
 
 use self::Error::*;
 
+/// The opaque libpcap capture handle, for passing handles to and from other libraries with
+/// `Capture::from_ptr` and `Capture::into_raw`.
+pub use raw::pcap_t;
+pub use linktype::LinktypeCategory;
+pub use parse::{ArpInfo, ArpOperation, DnsInfo, DnsQuestion, PktapMetadata, TcpFlags, TcpInfo,
+                VlanTag};
+
+/// Implements the set operations of a flags type wrapping `$bits`, whose flags are the
+/// associated constants listed, in the same way as the `bitflags` crate.
+macro_rules! impl_flags {
+    ($name:ident: $bits:ty { $($flag:ident),* }) => {
+        impl $name {
+            /// Returns a set with no flags.
+            pub fn empty() -> $name {
+                $name(0)
+            }
+
+            /// Returns the raw value of the flags.
+            pub fn bits(&self) -> $bits {
+                self.0
+            }
+
+            /// Converts a raw value, dropping any bits which do not correspond to a flag.
+            pub fn from_bits_truncate(bits: $bits) -> $name {
+                $name(bits & (0 $(| $name::$flag.0)*))
+            }
+
+            /// Returns whether all of the flags in `other` are set.
+            pub fn contains(&self, other: $name) -> bool {
+                self.0 & other.0 == other.0
+            }
+        }
+
+        impl std::ops::BitOr for $name {
+            type Output = $name;
+            fn bitor(self, other: $name) -> $name {
+                $name(self.0 | other.0)
+            }
+        }
+
+        impl std::ops::BitAnd for $name {
+            type Output = $name;
+            fn bitand(self, other: $name) -> $name {
+                $name(self.0 & other.0)
+            }
+        }
+    };
+}
+
+mod linktype;
+mod parse;
 mod raw;
 mod unique;
 #[cfg(feature = "capture-stream")]
 pub mod stream;
+#[cfg(feature = "flow")]
+pub mod flow;
+pub mod frame;
+pub mod pcapng;
+pub mod replay;
+pub mod ring;
+pub mod rotate;
+pub mod stats;
+pub mod writer;
 
 /// An error received from pcap
 #[derive(Debug, PartialEq)]
@@ -84,6 +150,15 @@ pub enum Error {
     IoError(std::io::ErrorKind),
     #[cfg(not(windows))]
     InvalidRawFd,
+    Unsupported,
+    PacketTooLarge { len: usize, max: usize },
+    Interrupted,
+    PartialSend { sent: usize, error: Box<Error> },
+    PrecisionMismatch { requested: Precision, actual: Precision },
+    ReadLimitExceeded,
+    CaptureClosed,
+    Activation { kind: ActivationError, message: String },
+    AppendMismatch { field: &'static str, existing: String, new: String },
 }
 
 impl Error {
@@ -93,6 +168,75 @@ impl Error {
             Ok(string) => PcapError(string.unwrap_or_default()),
         }
     }
+
+    /// Returns whether this is `TimeoutExpired`, i.e. no packets arrived on a live capture
+    /// before the read timeout expired.
+    pub fn is_timeout(&self) -> bool {
+        *self == TimeoutExpired
+    }
+
+    /// Returns whether this is `NoMorePackets`, i.e. the end of a savefile was reached.
+    pub fn is_eof(&self) -> bool {
+        *self == NoMorePackets
+    }
+
+    /// Returns whether this is an `IoError` of kind `WouldBlock`, as returned when polling a
+    /// non-blocking capture with no packets available.
+    pub fn is_would_block(&self) -> bool {
+        *self == IoError(io::ErrorKind::WouldBlock)
+    }
+
+    /// Classifies this error, recognising common failures from the messages of `PcapError`s as
+    /// well as from the kind of `Activation` errors. The message of a `PcapError` is kept, so
+    /// it can still be shown to users.
+    ///
+    /// libpcap's messages vary across versions and platforms, so unrecognised messages are
+    /// classified as `ErrorClass::Other`.
+    pub fn classify(&self) -> ErrorClass {
+        match *self {
+            Activation { kind: ActivationError::NoSuchDevice, .. } => ErrorClass::NoSuchDevice,
+            Activation { kind: ActivationError::PermissionDenied, .. }
+            | Activation { kind: ActivationError::PromiscPermissionDenied, .. } => {
+                ErrorClass::PermissionDenied
+            }
+            PcapError(ref msg) | Activation { message: ref msg, .. } => {
+                ErrorClass::from_message(msg)
+            }
+            _ => ErrorClass::Other,
+        }
+    }
+}
+
+/// A broad class of errors, as returned by `Error::classify`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorClass {
+    /// The device does not exist.
+    NoSuchDevice,
+    /// The process lacks the privileges to capture on the device.
+    PermissionDenied,
+    /// The device is in use by another process.
+    DeviceBusy,
+    Other,
+}
+
+impl ErrorClass {
+    fn from_message(msg: &str) -> ErrorClass {
+        const CLASSES: &[(&str, ErrorClass)] = &[
+            ("no such device", ErrorClass::NoSuchDevice),
+            ("doesn't exist", ErrorClass::NoSuchDevice),
+            ("cannot find the device", ErrorClass::NoSuchDevice),
+            ("permission", ErrorClass::PermissionDenied),
+            ("operation not permitted", ErrorClass::PermissionDenied),
+            ("access is denied", ErrorClass::PermissionDenied),
+            ("busy", ErrorClass::DeviceBusy),
+        ];
+        let msg = msg.to_lowercase();
+        CLASSES
+            .iter()
+            .find(|&&(pattern, _)| msg.contains(pattern))
+            .map(|&(_, class)| class)
+            .unwrap_or(ErrorClass::Other)
+    }
 }
 
 impl fmt::Display for Error {
@@ -110,6 +254,25 @@ impl fmt::Display for Error {
             IoError(ref e) => write!(f, "io error occurred: {:?}", e),
             #[cfg(not(windows))]
             InvalidRawFd => write!(f, "invalid raw file descriptor provided"),
+            Unsupported => write!(f, "operation not supported on this platform"),
+            PacketTooLarge { len, max } => {
+                write!(f, "packet of {} bytes exceeds the maximum of {} bytes", len, max)
+            }
+            Interrupted => write!(f, "interrupted by a signal while reading from a live capture"),
+            PartialSend { sent, ref error } => {
+                write!(f, "failed after sending {} packets: {}", sent, error)
+            }
+            PrecisionMismatch { requested, actual } => {
+                write!(f, "requested {:?} time stamp precision, but the capture provides {:?}",
+                       requested, actual)
+            }
+            ReadLimitExceeded => write!(f, "savefile exceeds the packet or byte limit"),
+            CaptureClosed => write!(f, "the capture handle has been closed"),
+            Activation { ref message, .. } => write!(f, "libpcap error: {}", message),
+            AppendMismatch { field, ref existing, ref new } => {
+                write!(f, "cannot append to savefile with {} {} from a capture with {} {}",
+                       field, existing, field, new)
+            }
         }
     }
 }
@@ -129,17 +292,90 @@ impl std::error::Error for Error {
             IoError(..) => "io error occurred",
             #[cfg(not(windows))]
             InvalidRawFd => "invalid raw file descriptor provided",
+            Unsupported => "operation not supported on this platform",
+            PacketTooLarge { .. } => "packet exceeds the maximum size",
+            Interrupted => "interrupted by a signal while reading from a live capture",
+            PartialSend { .. } => "failed to send all packets",
+            PrecisionMismatch { .. } => "requested time stamp precision is not supported",
+            ReadLimitExceeded => "savefile exceeds the packet or byte limit",
+            CaptureClosed => "the capture handle has been closed",
+            Activation { .. } => "failed to activate the capture",
+            AppendMismatch { .. } => "savefile does not match the capture it would be appended from",
         }
     }
 
     fn cause(&self) -> Option<&dyn std::error::Error> {
         match *self {
             MalformedError(ref e) => Some(e),
+            PartialSend { ref error, .. } => Some(&**error),
+            _ => None,
+        }
+    }
+}
+
+/// The reason a capture could not be activated, for failures which libpcap reports with a
+/// specific `PCAP_ERROR_*` status code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ActivationError {
+    /// The device does not exist (`PCAP_ERROR_NO_SUCH_DEVICE`).
+    NoSuchDevice,
+    /// The process lacks the privileges to capture on the device (`PCAP_ERROR_PERM_DENIED`).
+    PermissionDenied,
+    /// The process lacks the privileges to enable promiscuous mode
+    /// (`PCAP_ERROR_PROMISC_PERM_DENIED`).
+    PromiscPermissionDenied,
+    /// Monitor mode was requested, but the device does not support it
+    /// (`PCAP_ERROR_RFMON_NOTSUP`).
+    RfmonNotSupported,
+    /// The device is not up (`PCAP_ERROR_IFACE_NOT_UP`). On macOS, this is also reported when
+    /// monitor mode is requested for a Wi-Fi interface which is in use.
+    InterfaceNotUp,
+}
+
+impl ActivationError {
+    fn from_status(status: i32) -> Option<ActivationError> {
+        match status {
+            raw::PCAP_ERROR_NO_SUCH_DEVICE => Some(ActivationError::NoSuchDevice),
+            raw::PCAP_ERROR_PERM_DENIED => Some(ActivationError::PermissionDenied),
+            raw::PCAP_ERROR_PROMISC_PERM_DENIED => Some(ActivationError::PromiscPermissionDenied),
+            raw::PCAP_ERROR_RFMON_NOTSUP => Some(ActivationError::RfmonNotSupported),
+            raw::PCAP_ERROR_IFACE_NOT_UP => Some(ActivationError::InterfaceNotUp),
             _ => None,
         }
     }
 }
 
+/// A warning reported by libpcap when activating a capture, which did not prevent activation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+    /// The `PCAP_WARNING*` status code.
+    pub code: i32,
+    /// libpcap's message, which is more specific than the code, e.g. naming the device.
+    pub message: String,
+}
+
+impl Warning {
+    /// A warning without a more specific code (`PCAP_WARNING`), e.g. that the snaplen was
+    /// clamped.
+    pub const GENERIC: i32 = raw::PCAP_WARNING;
+    /// Promiscuous mode was requested but is not supported (`PCAP_WARNING_PROMISC_NOTSUP`).
+    pub const PROMISC_NOT_SUPPORTED: i32 = raw::PCAP_WARNING_PROMISC_NOTSUP;
+    /// The requested time stamp type is not supported (`PCAP_WARNING_TSTAMP_TYPE_NOTSUP`).
+    pub const TSTAMP_TYPE_NOT_SUPPORTED: i32 = raw::PCAP_WARNING_TSTAMP_TYPE_NOTSUP;
+
+    /// Creates a warning, using libpcap's description of the code if there is no message.
+    fn new(code: i32, message: String) -> Warning {
+        let message = if message.is_empty() { status_to_str(code) } else { message };
+        Warning { code, message }
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "libpcap warning: {}", self.message)
+    }
+}
+
 impl From<ffi::NulError> for Error {
     fn from(_: ffi::NulError) -> Error {
         InvalidInputString
@@ -158,22 +394,179 @@ impl From<std::io::Error> for Error {
     }
 }
 
+/// Converts an `IoError` back into an `io::Error` of the same kind, and other errors into
+/// an `io::Error` of kind `Other` with the same message.
+impl From<Error> for std::io::Error {
+    // `io::Error::other` is not available in the minimum supported rustc version
+    #[allow(clippy::io_other_error)]
+    fn from(obj: Error) -> std::io::Error {
+        match obj {
+            IoError(kind) => kind.into(),
+            err => std::io::Error::new(std::io::ErrorKind::Other, err.to_string()),
+        }
+    }
+}
+
 impl From<std::io::ErrorKind> for Error {
     fn from(obj: std::io::ErrorKind) -> Error {
         IoError(obj)
     }
 }
 
+/// The flags pcap reports for a network device (`PCAP_IF_*`).
+///
+/// Flags which are not supported by the installed libpcap version are never set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DeviceFlags(u32);
+
+impl DeviceFlags {
+    pub const LOOPBACK: DeviceFlags = DeviceFlags(0x0000_0001);
+    pub const UP: DeviceFlags = DeviceFlags(0x0000_0002);
+    pub const RUNNING: DeviceFlags = DeviceFlags(0x0000_0004);
+    pub const WIRELESS: DeviceFlags = DeviceFlags(0x0000_0008);
+    /// The two bits holding the `ConnectionStatus`.
+    pub const CONNECTION_STATUS: DeviceFlags = DeviceFlags(0x0000_0030);
+}
+
+impl_flags!(DeviceFlags: u32 { LOOPBACK, UP, RUNNING, WIRELESS, CONNECTION_STATUS });
+
+impl DeviceFlags {
+    pub fn is_loopback(&self) -> bool {
+        self.contains(DeviceFlags::LOOPBACK)
+    }
+
+    pub fn is_up(&self) -> bool {
+        self.contains(DeviceFlags::UP)
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.contains(DeviceFlags::RUNNING)
+    }
+
+    pub fn is_wireless(&self) -> bool {
+        self.contains(DeviceFlags::WIRELESS)
+    }
+
+    /// Decodes whether the device is connected to a network, e.g. whether a cable is plugged
+    /// in or a wireless adapter is associated.
+    pub fn connection_status(&self) -> ConnectionStatus {
+        match (*self & DeviceFlags::CONNECTION_STATUS).bits() {
+            0x10 => ConnectionStatus::Connected,
+            0x20 => ConnectionStatus::Disconnected,
+            0x30 => ConnectionStatus::NotApplicable,
+            _ => ConnectionStatus::Unknown,
+        }
+    }
+}
+
+/// Whether a device is connected to a network, as reported in its `DeviceFlags`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionStatus {
+    Unknown,
+    Connected,
+    Disconnected,
+    /// The device has no notion of being connected, e.g. a loopback device.
+    NotApplicable,
+}
+
+/// Which kind of device `Device::lookup_with` should prefer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LookupPref {
+    /// The first device which is up and is not a loopback device.
+    NonLoopbackUp,
+    /// The first device which is up.
+    AnyUp,
+    /// The first loopback device which is up.
+    Loopback,
+}
+
 #[derive(Debug, Clone)]
 /// A network device name and (potentially) pcap's description of it.
 pub struct Device {
     pub name: String,
     pub desc: Option<String>,
+    pub flags: DeviceFlags,
+}
+
+/// Devices are compared by name and description only, since their flags can change while
+/// the device remains the same.
+impl PartialEq for Device {
+    fn eq(&self, other: &Device) -> bool {
+        self.name == other.name && self.desc == other.desc
+    }
 }
 
+impl Eq for Device {}
+
 impl Device {
     fn new(name: String, desc: Option<String>) -> Device {
-        Device { name, desc }
+        Device { name, desc, flags: DeviceFlags::empty() }
+    }
+
+    /// Returns the macOS `pktap` pseudo-device, which captures on the given interfaces (or on
+    /// all interfaces if none are given) with a `PKTAP` header on every packet, recording the
+    /// interface and the process which sent or received it. See `Packet::pktap_metadata`.
+    #[cfg(target_os = "macos")]
+    pub fn pktap(interfaces: &[&str]) -> Device {
+        let mut name = String::from("pktap");
+        for interface in interfaces {
+            name.push(',');
+            name.push_str(interface);
+        }
+        Device::new(name, None)
+    }
+
+    /// Returns a device with the given name, exactly as it will be passed to pcap_create.
+    /// This is useful for names which do not appear in `Device::list()`, such as the
+    /// backend-prefixed names supported by some libpcap builds (e.g. `netmap:eth0` or
+    /// `dpdk:0`).
+    pub fn from_name_raw(name: &str) -> Device {
+        Device::new(name.to_string(), None)
+    }
+
+    /// Returns a name for showing the device to users: its description if pcap has one (it
+    /// usually does on Windows and macOS, but not on Linux), or otherwise its name along with
+    /// the kind of device suggested by its flags or name, e.g. "Loopback (lo)" or
+    /// "Wi-Fi (wlan0)". Devices of unknown kinds are shown by their name alone.
+    pub fn display_name(&self) -> String {
+        // Common Linux and BSD interface name prefixes
+        const KINDS: &[(&str, &str)] = &[
+            ("eth", "Ethernet"),
+            ("en", "Ethernet"),
+            ("wl", "Wi-Fi"),
+            ("ath", "Wi-Fi"),
+            ("veth", "Virtual Ethernet"),
+            ("br", "Bridge"),
+            ("virbr", "Bridge"),
+            ("docker", "Bridge"),
+            ("tun", "Tunnel"),
+            ("tap", "Tunnel"),
+            ("utun", "Tunnel"),
+            ("wg", "WireGuard"),
+            ("ppp", "PPP"),
+            ("bluetooth", "Bluetooth"),
+            ("usbmon", "USB bus"),
+            ("nflog", "Netfilter log"),
+            ("nfqueue", "Netfilter queue"),
+            ("dbus", "D-Bus"),
+        ];
+        match self.desc {
+            Some(ref desc) if !desc.is_empty() => return desc.clone(),
+            _ => {}
+        }
+        let kind = if self.flags.is_loopback() || self.name == "lo" || self.name == "lo0" {
+            Some("Loopback")
+        } else if self.name == "any" {
+            Some("All devices")
+        } else if self.flags.is_wireless() {
+            Some("Wi-Fi")
+        } else {
+            KINDS.iter().find(|&&(prefix, _)| self.name.starts_with(prefix)).map(|&(_, kind)| kind)
+        };
+        match kind {
+            Some(kind) => format!("{} ({})", kind, self.name),
+            None => self.name.clone(),
+        }
     }
 
     /// Opens a `Capture<Active>` on this device.
@@ -192,6 +585,31 @@ impl Device {
         })
     }
 
+    /// Returns a device from `Device::list()` chosen according to `prefer`, which is more
+    /// predictable than `lookup()` where some devices cannot capture (e.g. in containers).
+    ///
+    /// If no device matches the preference, the first device which is up is returned, and
+    /// failing that the first device. Returns a `PcapError` if there are no devices.
+    pub fn lookup_with(prefer: LookupPref) -> Result<Device, Error> {
+        Device::select(Device::list()?, prefer)
+            .ok_or_else(|| PcapError("no devices found".to_string()))
+    }
+
+    fn select(devices: Vec<Device>, prefer: LookupPref) -> Option<Device> {
+        let preferred = devices.iter().position(|device| {
+            let flags = device.flags;
+            flags.is_up() && match prefer {
+                LookupPref::NonLoopbackUp => !flags.is_loopback(),
+                LookupPref::AnyUp => true,
+                LookupPref::Loopback => flags.is_loopback(),
+            }
+        });
+        let index = preferred
+            .or_else(|| devices.iter().position(|device| device.flags.is_up()))
+            .unwrap_or(0);
+        devices.into_iter().nth(index)
+    }
+
     /// Returns a vector of `Device`s known by pcap via pcap_findalldevs.
     pub fn list() -> Result<Vec<Device>, Error> {
         with_errbuf(|err| unsafe {
@@ -199,21 +617,52 @@ impl Device {
             if raw::pcap_findalldevs(&mut dev_buf, err) != 0 {
                 return Err(Error::new(err));
             }
-            let result = (|| {
-                let mut devices = vec![];
-                let mut cur = dev_buf;
-                while !cur.is_null() {
-                    let dev = &*cur;
-                    devices.push(Device::new(cstr_to_string(dev.name)?.ok_or(InvalidString)?,
-                                             cstr_to_string(dev.description)?));
-                    cur = dev.next;
-                }
-                Ok(devices)
-            })();
+            let result = Device::from_if_list(dev_buf);
+            raw::pcap_freealldevs(dev_buf);
+            result
+        })
+    }
+
+    /// Returns the `Device` named `name` from `Device::list()`, or `None` if there is no such
+    /// device. Useful for checking that a configured interface still exists.
+    pub fn find(name: &str) -> Result<Option<Device>, Error> {
+        Ok(Device::list()?.into_iter().find(|device| device.name == name))
+    }
+
+    /// Returns a vector of `Device`s available from `source` via pcap_findalldevs_ex. The
+    /// source may be a remote host (`rpcap://host`), in which case `auth` is used to
+    /// authenticate to it, or a local directory of savefiles (`file://path/`).
+    #[cfg(libpcap_1_9_0)]
+    pub fn list_remote(source: &str, auth: Option<&RemoteAuth>) -> Result<Vec<Device>, Error> {
+        let source = CString::new(source)?;
+        let auth = auth.map(RawRemoteAuth::new).transpose()?;
+        let mut raw_auth = auth.as_ref().map(RawRemoteAuth::as_raw);
+        let auth_ptr = raw_auth.as_mut().map_or(ptr::null_mut(), |a| a as *mut _);
+        with_errbuf(|err| unsafe {
+            let mut dev_buf: *mut raw::pcap_if_t = ptr::null_mut();
+            if raw::pcap_findalldevs_ex(source.as_ptr(), auth_ptr, &mut dev_buf, err) != 0 {
+                return Err(Error::new(err));
+            }
+            let result = Device::from_if_list(dev_buf);
             raw::pcap_freealldevs(dev_buf);
             result
         })
     }
+
+    unsafe fn from_if_list(dev_buf: *mut raw::pcap_if_t) -> Result<Vec<Device>, Error> {
+        let mut devices = vec![];
+        let mut cur = dev_buf;
+        while !cur.is_null() {
+            let dev = &*cur;
+            devices.push(Device {
+                name: cstr_to_string(dev.name)?.ok_or(InvalidString)?,
+                desc: cstr_to_string(dev.description)?,
+                flags: DeviceFlags::from_bits_truncate(dev.flags),
+            });
+            cur = dev.next;
+        }
+        Ok(devices)
+    }
 }
 
 impl<'a> Into<Device> for &'a str {
@@ -224,8 +673,8 @@ impl<'a> Into<Device> for &'a str {
 
 /// This is a datalink link type.
 ///
-/// As an example, `Linktype(1)` is ethernet. A full list of linktypes is available
-/// [here](http://www.tcpdump.org/linktypes.html).
+/// As an example, `Linktype(1)` is ethernet, also available as `Linktype::ETHERNET`. A full
+/// list of linktypes is available [here](http://www.tcpdump.org/linktypes.html).
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub struct Linktype(pub i32);
 
@@ -257,6 +706,64 @@ impl<'a> Packet<'a> {
     pub fn new(header: &'a PacketHeader, data: &'a [u8]) -> Packet<'a> {
         Packet { header, data }
     }
+
+    /// Returns the bytes of the packet within `range`, or `None` if the range is out of
+    /// bounds. Unlike indexing, this never panics on short (e.g. truncated) packets.
+    pub fn get_range(&self, range: Range<usize>) -> Option<&'a [u8]> {
+        self.data.get(range)
+    }
+
+    /// Divides the packet data into two slices at `mid`, or returns `None` if `mid` is
+    /// past the end of the packet.
+    pub fn split_at_checked(&self, mid: usize) -> Option<(&'a [u8], &'a [u8])> {
+        if mid <= self.data.len() {
+            Some(self.data.split_at(mid))
+        } else {
+            None
+        }
+    }
+}
+
+impl<'a> Packet<'a> {
+    /// Returns a wrapper which formats the packet data as a hex dump, with the offset,
+    /// hexadecimal and printable ASCII representations of each 16 byte row, e.g.
+    ///
+    /// ```text
+    /// 0000  45 00 00 54 12 34 40 00 40 01 00 00 7f 00 00 01  E..T.4@.@.......
+    /// 0010  7f 00 00 01                                      ....
+    /// ```
+    pub fn hexdump(&self) -> HexDump<'a> {
+        HexDump(self.data)
+    }
+}
+
+/// Formats bytes as a hex dump. Created by `Packet::hexdump()`.
+pub struct HexDump<'a>(&'a [u8]);
+
+impl fmt::Display for HexDump<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, row) in self.0.chunks(16).enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{:04x} ", i * 16)?;
+            for byte in row {
+                write!(f, " {:02x}", byte)?;
+            }
+            write!(f, "{:width$}  ", "", width = (16 - row.len()) * 3)?;
+            for &byte in row {
+                let c = if byte.is_ascii_graphic() || byte == b' ' { byte as char } else { '.' };
+                write!(f, "{}", c)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for HexDump<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
 }
 
 impl<'b> Deref for Packet<'b> {
@@ -267,6 +774,61 @@ impl<'b> Deref for Packet<'b> {
     }
 }
 
+impl AsRef<[u8]> for Packet<'_> {
+    fn as_ref(&self) -> &[u8] {
+        self.data
+    }
+}
+
+impl<'b> IntoIterator for &Packet<'b> {
+    type Item = &'b u8;
+    type IntoIter = slice::Iter<'b, u8>;
+
+    fn into_iter(self) -> slice::Iter<'b, u8> {
+        self.data.iter()
+    }
+}
+
+/// The outcome of `Capture::next_ex`, mirroring the return codes of `pcap_next_ex`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NextResult<'a> {
+    /// A packet was read (return code 1).
+    Packet(Packet<'a>),
+    /// The read timeout of a live capture expired before any packets arrived (return code 0).
+    Timeout,
+    /// The end of a savefile was reached (return code -2).
+    Eof,
+}
+
+/// An owned copy of a `Packet`, which can outlive the capture handle's buffer (e.g. to be
+/// stored or sent to another thread).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PacketOwned {
+    pub header: PacketHeader,
+    pub data: Vec<u8>,
+}
+
+impl PacketOwned {
+    /// Borrows this packet as a `Packet`.
+    pub fn as_packet(&self) -> Packet<'_> {
+        Packet::new(&self.header, &self.data)
+    }
+}
+
+impl<'a> From<Packet<'a>> for PacketOwned {
+    fn from(packet: Packet<'a>) -> PacketOwned {
+        PacketOwned { header: *packet.header, data: packet.data.to_vec() }
+    }
+}
+
+impl Deref for PacketOwned {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.data
+    }
+}
+
 #[repr(C)]
 #[derive(Copy, Clone)]
 /// Represents a packet header provided by pcap, including the timeval, caplen and len.
@@ -276,6 +838,19 @@ pub struct PacketHeader {
     pub len: u32,
 }
 
+impl PacketHeader {
+    /// Returns whether fewer bytes were captured than were on the wire, e.g. because the
+    /// packet was longer than the snaplen.
+    pub fn is_truncated(&self) -> bool {
+        self.caplen < self.len
+    }
+
+    /// Returns the number of bytes of the packet which were not captured.
+    pub fn truncated_bytes(&self) -> u32 {
+        self.len.saturating_sub(self.caplen)
+    }
+}
+
 impl fmt::Debug for PacketHeader {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f,
@@ -307,6 +882,66 @@ impl Stat {
     fn new(received: u32, dropped: u32, if_dropped: u32) -> Stat {
         Stat { received, dropped, if_dropped }
     }
+
+    /// Returns the counts accumulated since `baseline`, allowing for the counters wrapping.
+    fn since(&self, baseline: &Stat) -> Stat {
+        Stat::new(self.received.wrapping_sub(baseline.received),
+                  self.dropped.wrapping_sub(baseline.dropped),
+                  self.if_dropped.wrapping_sub(baseline.if_dropped))
+    }
+
+    /// Returns the number of packets dropped by the kernel for each packet received, or the
+    /// number dropped if none were received.
+    pub fn drop_rate(&self) -> f64 {
+        f64::from(self.dropped) / f64::from(self.received.max(1))
+    }
+}
+
+/// Formats the counters for logging, e.g. `recv=1000 drop=5 ifdrop=0`.
+impl fmt::Display for Stat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "recv={} drop={} ifdrop={}", self.received, self.dropped, self.if_dropped)
+    }
+}
+
+/// A single instruction of a classic BPF program, laid out as libpcap's `struct bpf_insn`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BpfInstruction {
+    pub code: u16,
+    pub jt: u8,
+    pub jf: u8,
+    pub k: u32,
+}
+
+/// The extended statistics reported by WinPcap/Npcap's `pcap_stats_ex`.
+///
+/// Counters which the driver does not report are zero.
+#[cfg(windows)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct StatEx {
+    pub received: u32,
+    pub dropped: u32,
+    pub if_dropped: u32,
+    /// Packets which passed the filter and were captured.
+    pub captured: u32,
+    /// Packets sent on the interface.
+    pub sent: u32,
+    /// Packets dropped by the network before reaching the interface.
+    pub net_dropped: u32,
+}
+
+/// A BPF program compiled by `pcap_compile`, which is freed when dropped.
+struct BpfProgram(raw::bpf_program);
+
+// The program is only ever read after compilation
+unsafe impl Send for BpfProgram {}
+unsafe impl Sync for BpfProgram {}
+
+impl Drop for BpfProgram {
+    fn drop(&mut self) {
+        unsafe { raw::pcap_freecode(&mut self.0) }
+    }
 }
 
 #[repr(u32)]
@@ -316,6 +951,15 @@ pub enum Precision {
     Nano = 1,
 }
 
+/// The format of a savefile, as returned by `Capture::file_format`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum FileFormat {
+    /// A classic pcap savefile, with microsecond or (if `nanos` is set) nanosecond time stamps.
+    Pcap { nanos: bool },
+    /// A pcapng savefile, which may contain packets from several interfaces and link types.
+    PcapNg,
+}
+
 /// Phantom type representing an inactive capture handle.
 pub enum Inactive {}
 
@@ -353,6 +997,10 @@ unsafe impl State for Offline {}
 
 unsafe impl State for Dead {}
 
+/// The value of `Capture::datalink` when the datalink type has not been cached. Datalink types
+/// are never negative.
+const DATALINK_UNKNOWN: i32 = -1;
+
 /// This is a pcap capture handle which is an abstraction over the `pcap_t` provided by pcap.
 /// There are many ways to instantiate and interact with a pcap handle, so phantom types are
 /// used to express these behaviors.
@@ -386,6 +1034,28 @@ unsafe impl State for Dead {}
 /// ```
 pub struct Capture<T: State + ? Sized> {
     nonblock: bool,
+    stats_baseline: Stat,
+    max_packet_len: usize,
+    // Where an offline capture was opened from, for `rewind()` and `try_clone()`
+    data_offset: Option<libc::c_long>,
+    path: Option<PathBuf>,
+    requested_precision: Option<Precision>,
+    requested_snaplen: Option<i32>,
+    filter_cache: HashMap<String, BpfProgram>,
+    // The expression of the installed filter, for recompiling it in `set_datalink()`
+    filter: Option<String>,
+    // The program installed with `set_filter_raw()`, for reinstalling it in `rewind()`
+    filter_raw: Option<Vec<BpfInstruction>>,
+    // Atomic because `direction()` takes `&self`
+    direction: AtomicU32,
+    // The datalink type returned by `get_datalink()`, or `DATALINK_UNKNOWN` until it is first
+    // called and after `set_datalink()`
+    datalink: AtomicI32,
+    // Set by `from_file_with_linktype()` when libpcap refuses to change the datalink type
+    linktype_override: Option<Linktype>,
+    // Held around every call to pcap_stats, and cleared before the handle is closed, for
+    // `StatsHandle`s which outlive the capture
+    alive: Arc<Mutex<bool>>,
     handle: Unique<raw::pcap_t>,
     _marker: PhantomData<T>,
 }
@@ -395,12 +1065,51 @@ impl<T: State + ? Sized> Capture<T> {
         unsafe {
             Capture {
                 nonblock: false,
+                stats_baseline: Stat::new(0, 0, 0),
+                max_packet_len: 65535,
+                data_offset: None,
+                path: None,
+                requested_precision: None,
+                requested_snaplen: None,
+                filter_cache: HashMap::new(),
+                filter: None,
+                filter_raw: None,
+                direction: AtomicU32::new(Direction::InOut as u32),
+                datalink: AtomicI32::new(DATALINK_UNKNOWN),
+                linktype_override: None,
+                alive: Arc::new(Mutex::new(true)),
                 handle: Unique::new(handle),
                 _marker: PhantomData,
             }
         }
     }
 
+    /// Wraps a capture handle created outside of this crate, e.g. by another library using
+    /// libpcap, and takes ownership of it.
+    ///
+    /// # Safety
+    ///
+    /// `handle` must be a valid, non-null handle returned by libpcap, in the state `T`
+    /// describes: for example, a handle passed as `Capture<Active>` must have been activated,
+    /// and one passed as `Capture<Dead>` must come from `pcap_open_dead`. The `Capture` closes
+    /// the handle with `pcap_close` when dropped, so it must not be closed or used elsewhere
+    /// afterwards.
+    pub unsafe fn from_ptr(handle: *mut pcap_t) -> Capture<T> {
+        let mut cap = Capture::new(handle);
+        cap.nonblock = with_errbuf(|err| Ok(raw::pcap_getnonblock(handle, err) == 1))
+            .unwrap_or(false);
+        cap
+    }
+
+    /// Releases the underlying capture handle without closing it, e.g. to pass it to another
+    /// library using libpcap. The caller becomes responsible for closing the handle with
+    /// `pcap_close`, or for wrapping it again with `from_ptr`.
+    pub fn into_raw(mut self) -> *mut pcap_t {
+        // Dropping the capture with a null handle drops every other field without closing it
+        let handle = mem::replace(&mut self.handle, unsafe { Unique::new(ptr::null_mut()) });
+        *handle
+    }
+
     fn new_raw<F>(path: Option<&str>, func: F) -> Result<Capture<T>, Error>
     where F: FnOnce(*const libc::c_char, *mut libc::c_char) -> *mut raw::pcap_t
     {
@@ -416,14 +1125,47 @@ impl<T: State + ? Sized> Capture<T> {
         })
     }
 
-    /// Set the minumum amount of data received by the kernel in a single call.
+    /// Set the minumum amount of data received by the kernel in a single call, so that packets
+    /// are delivered in fewer, larger batches.
     ///
     /// Note that this value is set to 0 when the capture is set to immediate mode. You should not
     /// call `min_to_copy` on captures in immediate mode if you want them to stay in immediate mode.
-    #[cfg(windows)]
-    pub fn min_to_copy(self, to: i32) -> Capture<T> {
-        unsafe { raw::pcap_setmintocopy(*self.handle, to as _); }
-        self
+    ///
+    /// This is only supported on Windows, where it is implemented with `pcap_setmintocopy`, and
+    /// fails for savefiles. Other platforms have no equivalent for capture sockets (Linux
+    /// delivers packets to the ring buffer as they arrive, and wakes readers according to the
+    /// timeout), and return `Unsupported`; a longer `timeout()` is the closest way to batch
+    /// wakeups there.
+    pub fn min_to_copy(&self, bytes: i32) -> Result<(), Error> {
+        #[cfg(windows)]
+        {
+            self.check_err(unsafe { raw::pcap_setmintocopy(*self.handle, bytes) == 0 })
+        }
+        #[cfg(not(windows))]
+        {
+            let _ = bytes;
+            Err(Unsupported)
+        }
+    }
+
+    /// Returns the message libpcap recorded for the most recent error on this handle, or
+    /// `None` if there is none.
+    ///
+    /// The message is only meaningful immediately after a call which failed: libpcap does not
+    /// clear it on success, and later calls may overwrite it.
+    pub fn last_error(&self) -> Option<String> {
+        let ptr = unsafe { raw::pcap_geterr(*self.handle) };
+        if ptr.is_null() {
+            return None;
+        }
+        let msg = unsafe { CStr::from_ptr(ptr) }.to_string_lossy();
+        if msg.is_empty() { None } else { Some(msg.into_owned()) }
+    }
+
+    /// Marks the handle as closed for any `StatsHandle`s, waiting for calls in progress.
+    fn close_stats_handles(&self) {
+        let mut alive = self.alive.lock().unwrap_or_else(|e| e.into_inner());
+        *alive = false;
     }
 
     #[inline]
@@ -434,13 +1176,104 @@ impl<T: State + ? Sized> Capture<T> {
             Err(Error::new(unsafe { raw::pcap_geterr(*self.handle) }))
         }
     }
+
+    /// Converts a non-zero status code returned by libpcap into an error, using libpcap's
+    /// description of the code if it did not set an error message on the handle.
+    fn status_err(&self, status: i32) -> Error {
+        match Error::new(unsafe { raw::pcap_geterr(*self.handle) }) {
+            PcapError(ref msg) if msg.is_empty() => PcapError(status_to_str(status)),
+            err => err,
+        }
+    }
+
+    /// Converts a status code returned by pcap_activate into an error, which is `Activation`
+    /// for the failures that `ActivationError` distinguishes.
+    fn activation_err(&self, status: i32) -> Error {
+        match (ActivationError::from_status(status), self.status_err(status)) {
+            (Some(kind), PcapError(message)) => Activation { kind, message },
+            (_, err) => err,
+        }
+    }
 }
 
 impl Capture<Offline> {
     /// Opens an offline capture handle from a pcap dump file, given a path.
+    ///
+    /// If libpcap cannot read the file and it is gzip-compressed, it is decompressed in memory
+    /// and read from there instead. This requires the `gzip` feature, and is not supported on
+    /// Windows, where an error is returned for such files.
     pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Capture<Offline>, Error> {
-        Capture::new_raw(path.as_ref().to_str(),
+        let path = path.as_ref();
+        Capture::new_raw(path.to_str(),
                          |path, err| unsafe { raw::pcap_open_offline(path, err) })
+            .map(|cap| cap.with_origin(Some(path)))
+            .or_else(|e| if is_gzip(path) { Capture::from_gzip_file(path) } else { Err(e) })
+    }
+
+    /// Opens an offline capture handle from a pcap dump file, decoding its packets as
+    /// `linktype` rather than the link type recorded in the file, e.g. to read mislabeled
+    /// captures.
+    ///
+    /// libpcap usually refuses to change the datalink type of a savefile, in which case the
+    /// override is only reported by `get_datalink()` and used by the methods which parse
+    /// packets according to it, such as `next_parsed()`. Filters are still compiled for the
+    /// link type recorded in the file.
+    ///
+    /// Returns `InvalidLinktype` if the link type is not known to libpcap or this crate.
+    pub fn from_file_with_linktype<P: AsRef<Path>>(path: P, linktype: Linktype)
+        -> Result<Capture<Offline>, Error>
+    {
+        if linktype.get_name().is_err() && !linktype.is_known() {
+            return Err(InvalidLinktype);
+        }
+        let mut cap = Capture::from_file(path)?;
+        if cap.set_datalink(linktype).is_err() {
+            cap.linktype_override = Some(linktype);
+        }
+        Ok(cap)
+    }
+
+    #[cfg(all(feature = "gzip", not(windows)))]
+    fn from_gzip_file(path: &Path) -> Result<Capture<Offline>, Error> {
+        use std::io::Read;
+
+        let mut data = vec![];
+        flate2::read::GzDecoder::new(std::fs::File::open(path)?).read_to_end(&mut data)?;
+        Capture::from_bytes(&data)
+    }
+
+    #[cfg(all(not(feature = "gzip"), not(windows)))]
+    fn from_gzip_file(_: &Path) -> Result<Capture<Offline>, Error> {
+        Err(PcapError("savefile is gzip-compressed, which requires the `gzip` feature".into()))
+    }
+
+    #[cfg(windows)]
+    fn from_gzip_file(_: &Path) -> Result<Capture<Offline>, Error> {
+        Err(PcapError("savefile is gzip-compressed, which is not supported on Windows".into()))
+    }
+
+    /// Opens an offline capture handle from the contents of a pcap dump file held in memory.
+    /// The data is copied into an anonymous temporary file which libpcap reads from.
+    #[cfg(not(windows))]
+    pub fn from_bytes(data: &[u8]) -> Result<Capture<Offline>, Error> {
+        unsafe {
+            let file = libc::tmpfile();
+            if file.is_null() {
+                return Err(io::Error::last_os_error().into());
+            }
+            if libc::fwrite(data.as_ptr() as _, 1, data.len(), file) != data.len()
+                || libc::fseek(file, 0, libc::SEEK_SET) != 0 {
+                let err = io::Error::last_os_error();
+                libc::fclose(file);
+                return Err(err.into());
+            }
+            let result = Capture::new_raw(None, |_, err| raw::pcap_fopen_offline(file, err))
+                .map(|cap| cap.with_origin(None));
+            if result.is_err() {
+                libc::fclose(file);
+            }
+            result
+        }
     }
 
     /// Opens an offline capture handle from a pcap dump file, given a path.
@@ -449,7 +1282,7 @@ impl Capture<Offline> {
     pub fn from_file_with_precision<P: AsRef<Path>>(path: P, precision: Precision) -> Result<Capture<Offline>, Error> {
         Capture::new_raw(path.as_ref().to_str(), |path, err| unsafe {
             raw::pcap_open_offline_with_tstamp_precision(path, precision as _, err)
-        })
+        }).map(|cap| cap.with_origin(Some(path.as_ref())))
     }
 
     /// Opens an offline capture handle from a pcap dump file, given a file descriptor.
@@ -459,6 +1292,7 @@ impl Capture<Offline> {
             .and_then(|file| Capture::new_raw(None, |_, err| unsafe {
                 raw::pcap_fopen_offline(file, err)
             }))
+            .map(|cap| cap.with_origin(None))
     }
 
     /// Opens an offline capture handle from a pcap dump file, given a file descriptor.
@@ -469,8 +1303,284 @@ impl Capture<Offline> {
             .and_then(|file| Capture::new_raw(None, |_, err| unsafe {
                 raw::pcap_fopen_offline_with_tstamp_precision(file, precision as _, err)
             }))
+            .map(|cap| cap.with_origin(None))
     }
-}
+
+    /// Returns the format of the savefile at `path`, determined from its magic number, without
+    /// opening a capture handle. Returns a `PcapError` if the file is not a pcap or pcapng
+    /// savefile.
+    pub fn file_format<P: AsRef<Path>>(path: P) -> Result<FileFormat, Error> {
+        use std::io::Read;
+
+        let mut magic = [0u8; 4];
+        std::fs::File::open(path)?.read_exact(&mut magic)?;
+        match u32::from_le_bytes(magic) {
+            0xa1b2_c3d4 | 0xd4c3_b2a1 => Ok(FileFormat::Pcap { nanos: false }),
+            0xa1b2_3c4d | 0x4d3c_b2a1 => Ok(FileFormat::Pcap { nanos: true }),
+            // The section header block type is a palindrome, so it reads the same in either
+            // byte order
+            0x0a0d_0d0a => Ok(FileFormat::PcapNg),
+            _ => Err(PcapError("unknown file format".to_string())),
+        }
+    }
+
+    /// Returns to the first packet of the savefile, so that it can be read again.
+    ///
+    /// pcap savefiles are rewound by seeking the underlying file back to just after the file
+    /// header. Other formats (such as pcapng) are rewound by reopening the file, and setting
+    /// the datalink type and filter of this handle on the new one, which is only supported
+    /// when the capture was opened from a path. Otherwise `Unsupported` is returned. If the
+    /// datalink type or filter cannot be set, the error is returned and this handle is left
+    /// as it was.
+    pub fn rewind(&mut self) -> Result<(), Error> {
+        if let Some(offset) = self.data_offset {
+            let file = unsafe { raw::pcap_file(*self.handle) };
+            if unsafe { libc::fseek(file, offset, libc::SEEK_SET) } != 0 {
+                return Err(io::Error::last_os_error().into());
+            }
+            return Ok(());
+        }
+        let mut cap = self.try_clone()?;
+        let datalink = unsafe { Linktype(raw::pcap_datalink(*self.handle)) };
+        if unsafe { raw::pcap_datalink(*cap.handle) } != datalink.0 {
+            cap.set_datalink(datalink)?;
+        }
+        if let Some(ref program) = self.filter {
+            cap.filter(program)?;
+        } else if let Some(ref insns) = self.filter_raw {
+            cap.set_filter_raw(insns)?;
+        }
+        mem::swap(&mut self.handle, &mut cap.handle);
+        self.datalink.store(DATALINK_UNKNOWN, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Opens a second, independent handle on the same savefile, positioned at its first
+    /// packet and with the same time stamp precision. Filters and datalink types set on this
+    /// handle are not copied.
+    ///
+    /// The savefile is reopened by path, so `Unsupported` is returned if this capture was not
+    /// opened from a path (e.g. it was opened from a raw file descriptor).
+    pub fn try_clone(&self) -> Result<Capture<Offline>, Error> {
+        let path = self.path.as_ref().ok_or(Unsupported)?;
+        #[cfg(libpcap_1_5_0)]
+        return Capture::from_file_with_precision(path, self.get_precision());
+        #[cfg(not(libpcap_1_5_0))]
+        return Capture::from_file(path);
+    }
+
+    /// Counts the packets which pass the current filter from the current position to the end
+    /// of the savefile, then returns to the first packet with `rewind()`. Useful for reporting
+    /// progress, at the cost of reading the file twice.
+    pub fn count_packets(&mut self) -> Result<u64, Error> {
+        let mut count = 0;
+        loop {
+            match self.next() {
+                Ok(_) => count += 1,
+                Err(NoMorePackets) => break,
+                Err(e) => return Err(e),
+            }
+        }
+        self.rewind()?;
+        Ok(count)
+    }
+
+    /// Returns whether the time stamps of the packets from the current position to the end
+    /// of the savefile never decrease, then returns to the first packet with `rewind()`.
+    /// Useful for warning about reordered captures before processing them.
+    pub fn check_monotonic(&mut self) -> Result<bool, Error> {
+        let mut last = None;
+        let mut monotonic = true;
+        loop {
+            match self.next() {
+                Ok(packet) => {
+                    let ts = (packet.header.ts.tv_sec, packet.header.ts.tv_usec);
+                    match last {
+                        Some(last) if ts < last => {
+                            monotonic = false;
+                            break;
+                        }
+                        _ => {}
+                    }
+                    last = Some(ts);
+                }
+                Err(NoMorePackets) => break,
+                Err(e) => return Err(e),
+            }
+        }
+        self.rewind()?;
+        Ok(monotonic)
+    }
+
+    /// Reads all remaining packets into memory. To avoid exhausting memory on large files,
+    /// returns `ReadLimitExceeded` if there are more than `max_packets` packets, or their data
+    /// totals more than `max_bytes` bytes.
+    pub fn read_all(&mut self, max_packets: Option<usize>, max_bytes: Option<usize>)
+        -> Result<Vec<PacketOwned>, Error>
+    {
+        let mut packets = vec![];
+        let mut bytes = 0;
+        loop {
+            let packet = match self.next() {
+                Ok(packet) => packet,
+                Err(NoMorePackets) => return Ok(packets),
+                Err(e) => return Err(e),
+            };
+            bytes += packet.len();
+            let exceeded = |count, max: Option<usize>| match max {
+                Some(max) => count > max,
+                None => false,
+            };
+            if exceeded(packets.len() + 1, max_packets) || exceeded(bytes, max_bytes) {
+                return Err(ReadLimitExceeded);
+            }
+            packets.push(packet.into());
+        }
+    }
+
+    /// Records where the savefile was opened from: the current position in the file if the
+    /// handle is reading a seekable pcap savefile, and the path if there is one.
+    fn with_origin(mut self, path: Option<&Path>) -> Capture<Offline> {
+        self.data_offset = unsafe { pcap_data_offset(raw::pcap_file(*self.handle)) };
+        self.path = path.map(Path::to_owned);
+        self
+    }
+}
+
+/// Returns the current position of `file` if it immediately follows a pcap file header, as it
+/// does after libpcap has opened a pcap (but not pcapng) savefile.
+unsafe fn pcap_data_offset(file: *mut libc::FILE) -> Option<libc::c_long> {
+    const MAGICS: [u32; 4] = [0xa1b2_c3d4, 0xd4c3_b2a1, 0xa1b2_3c4d, 0x4d3c_b2a1];
+    let header_len = mem::size_of::<raw::pcap_file_header>() as libc::c_long;
+
+    if file.is_null() {
+        return None;
+    }
+    let offset = libc::ftell(file);
+    if offset < header_len || libc::fseek(file, offset - header_len, libc::SEEK_SET) != 0 {
+        return None;
+    }
+    let mut magic = [0u8; 4];
+    let read = libc::fread(magic.as_mut_ptr() as _, 1, magic.len(), file);
+    if libc::fseek(file, offset, libc::SEEK_SET) != 0 {
+        return None;
+    }
+    if read == magic.len() && MAGICS.contains(&u32::from_le_bytes(magic)) {
+        Some(offset)
+    } else {
+        None
+    }
+}
+
+/// A capture source, as accepted by `Capture::open_url()`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Source {
+    /// A device on a remote host running `rpcapd`, e.g. `rpcap://host:2002/eth0`.
+    Remote { host: String, device: String },
+    /// A savefile on the local filesystem, e.g. `file:///tmp/capture.pcap`.
+    File(PathBuf),
+    /// A local device name, e.g. `eth0`.
+    Device(String),
+}
+
+impl Source {
+    /// Determines the kind of source named by `url`. Anything that is not an `rpcap://` or
+    /// `file://` URL is treated as a local device name.
+    pub fn parse(url: &str) -> Source {
+        let mut parts = url.splitn(2, "://");
+        match (parts.next(), parts.next()) {
+            (Some("rpcap"), Some(rest)) => {
+                let mut parts = rest.splitn(2, '/');
+                Source::Remote {
+                    host: parts.next().unwrap_or_default().to_owned(),
+                    device: parts.next().unwrap_or_default().to_owned(),
+                }
+            }
+            (Some("file"), Some(path)) => Source::File(PathBuf::from(path)),
+            _ => Source::Device(url.to_owned()),
+        }
+    }
+}
+
+/// The authentication method used to connect to a remote `rpcapd` server.
+#[repr(i32)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum AuthType {
+    Null = 0,
+    Password = 1,
+}
+
+/// Credentials for a remote capture source, matching libpcap's `struct pcap_rmtauth`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct RemoteAuth {
+    pub auth_type: AuthType,
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+/// Owns the strings referenced by a `pcap_rmtauth`, which must outlive the FFI call it is
+/// passed to.
+#[cfg(libpcap_1_9_0)]
+struct RawRemoteAuth {
+    username: Option<CString>,
+    password: Option<CString>,
+    auth_type: AuthType,
+}
+
+#[cfg(libpcap_1_9_0)]
+impl RawRemoteAuth {
+    fn new(auth: &RemoteAuth) -> Result<RawRemoteAuth, Error> {
+        Ok(RawRemoteAuth {
+            username: auth.username.as_ref().map(|s| CString::new(s.as_str())).transpose()?,
+            password: auth.password.as_ref().map(|s| CString::new(s.as_str())).transpose()?,
+            auth_type: auth.auth_type,
+        })
+    }
+
+    fn as_raw(&self) -> raw::pcap_rmtauth {
+        fn as_ptr(s: &Option<CString>) -> *mut libc::c_char {
+            s.as_ref().map_or(ptr::null_mut(), |s| s.as_ptr() as _)
+        }
+        raw::pcap_rmtauth {
+            type_: self.auth_type as _,
+            username: as_ptr(&self.username),
+            password: as_ptr(&self.password),
+        }
+    }
+}
+
+#[cfg(libpcap_1_9_0)]
+impl Capture<dyn Activated> {
+    /// Opens a capture handle from a source URL, which may name a device on a remote host
+    /// (`rpcap://host/device`), a savefile (`file://path`) or a local device (`eth0`).
+    ///
+    /// Remote captures are opened through `pcap_open`, and require a libpcap built with
+    /// remote capture support.
+    pub fn open_url(url: &str) -> Result<Capture<dyn Activated>, Error> {
+        Capture::open_source(url, None)
+    }
+
+    /// Like `open_url()`, but authenticates to a remote `rpcapd` server with the given
+    /// credentials. The credentials are ignored for local sources.
+    pub fn open_url_with_auth(url: &str, auth: &RemoteAuth) -> Result<Capture<dyn Activated>, Error> {
+        Capture::open_source(url, Some(auth))
+    }
+
+    fn open_source(url: &str, auth: Option<&RemoteAuth>) -> Result<Capture<dyn Activated>, Error> {
+        match Source::parse(url) {
+            Source::Remote { .. } => {
+                let auth = auth.map(RawRemoteAuth::new).transpose()?;
+                let mut raw_auth = auth.as_ref().map(RawRemoteAuth::as_raw);
+                let auth_ptr = raw_auth.as_mut().map_or(ptr::null_mut(), |a| a as *mut _);
+                Capture::<Active>::new_raw(Some(url), |url, err| unsafe {
+                    raw::pcap_open(url, 65535, 0, 0, auth_ptr, err)
+                }).map(Into::into)
+            }
+            Source::File(path) => Capture::from_file(path).map(Into::into),
+            Source::Device(name) => Capture::from_device(name.as_str())?.open().map(Into::into),
+        }
+    }
+}
 
 #[repr(i32)]
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
@@ -493,10 +1603,20 @@ pub enum Direction {
     Out = raw::PCAP_D_OUT,
 }
 
+/// libpcap captures packets in both directions unless told otherwise.
+impl Default for Direction {
+    fn default() -> Direction {
+        Direction::InOut
+    }
+}
+
 impl Capture<Inactive> {
     /// Opens a capture handle for a device. You can pass a `Device` or an `&str` device
     /// name here. The handle is inactive, but can be activated via `.open()`.
     ///
+    /// The name is passed to pcap_create unchanged, so names with a backend prefix (such as
+    /// `netmap:eth0`) can be used where libpcap was built with support for them.
+    ///
     /// # Example
     /// ```
     /// use pcap::*;
@@ -519,12 +1639,60 @@ impl Capture<Inactive> {
     }
 
     /// Activates an inactive capture created from `Capture::from_device()` or returns
-    /// an error.
+    /// an error. Warnings from libpcap (e.g. that promiscuous mode is not supported) do not
+    /// prevent activation and are ignored; use `open_with_warning()` to see them.
     pub fn open(self) -> Result<Capture<Active>, Error> {
-        unsafe {
-            self.check_err(raw::pcap_activate(*self.handle) == 0)?;
-            Ok(mem::transmute(self))
+        let (cap, _) = self.activate().map_err(|(_, e)| e)?;
+        #[cfg(libpcap_1_5_0)]
+        cap.check_precision()?;
+        Ok(cap)
+    }
+
+    /// Activates an inactive capture like `open()`, but returns the capture along with any
+    /// warnings rather than failing. These include warnings reported by libpcap (e.g. that
+    /// promiscuous mode is not supported), and settings which did not take effect as requested,
+    /// such as a snaplen which was clamped.
+    pub fn open_with_warning(self) -> Result<(Capture<Active>, Vec<Warning>), Error> {
+        let (cap, warning) = self.activate().map_err(|(_, e)| e)?;
+        let mut warnings: Vec<Warning> = warning.into_iter().collect();
+        #[cfg(libpcap_1_5_0)]
+        {
+            if let Err(e) = cap.check_precision() {
+                warnings.push(Warning::new(raw::PCAP_WARNING, e.to_string()));
+            }
+        }
+        warnings.extend(cap.check_snaplen());
+        Ok((cap, warnings))
+    }
+
+    /// Activates an inactive capture like `open()`, but if activation fails the inactive
+    /// capture is returned along with the error, so that its settings can be changed (e.g.
+    /// turning off promiscuous mode after `PromiscPermissionDenied`) before trying again.
+    ///
+    /// As with `open()`, warnings are ignored; use `open_with_warning()` to see them.
+    #[allow(clippy::result_large_err)]
+    pub fn open_or_return(self) -> Result<Capture<Active>, (Capture<Inactive>, Error)> {
+        self.activate().map(|(cap, _)| cap)
+    }
+
+    /// Activates the capture with pcap_activate. Any non-negative status is a success, which
+    /// is returned with a warning if it is positive, on every platform. On failure, the
+    /// capture is returned as libpcap leaves it inactive.
+    #[allow(clippy::result_large_err, clippy::type_complexity)]
+    fn activate(self) -> Result<(Capture<Active>, Option<Warning>), (Capture<Inactive>, Error)> {
+        let status = unsafe { raw::pcap_activate(*self.handle) };
+        if status < 0 {
+            let err = self.activation_err(status);
+            return Err((self, err));
         }
+        // The message must be read before any other call on the handle can overwrite it
+        let warning = if status > 0 {
+            Some(Warning::new(status, self.last_error().unwrap_or_default()))
+        } else {
+            None
+        };
+        let cap: Capture<Active> = unsafe { mem::transmute(self) };
+        Ok((cap, warning))
     }
 
     /// Set the read timeout for the Capture. By default, this is 0, so it will block
@@ -586,27 +1754,115 @@ impl Capture<Inactive> {
     }
 
     /// Set the time stamp precision returned in captures.
+    ///
+    /// If the device does not support the precision, `open()` fails with `PrecisionMismatch`
+    /// and `open_with_warning()` returns a warning, rather than silently falling back to
+    /// another precision.
     #[cfg(libpcap_1_5_0)]
-    pub fn precision(self, precision: Precision) -> Capture<Inactive> {
+    pub fn precision(mut self, precision: Precision) -> Capture<Inactive> {
         unsafe { raw::pcap_set_tstamp_precision(*self.handle, precision as _) };
+        self.requested_precision = Some(precision);
         self
     }
 
     /// Set the snaplen size (the maximum length of a packet captured into the buffer).
     /// Useful if you only want certain headers, but not the entire packet.
     ///
-    /// The default is 65535.
-    pub fn snaplen(self, to: i32) -> Capture<Inactive> {
+    /// The default is 65535. libpcap may clamp the snaplen when the capture is activated;
+    /// `open_with_warning()` reports a warning if it does.
+    pub fn snaplen(mut self, to: i32) -> Capture<Inactive> {
         unsafe { raw::pcap_set_snaplen(*self.handle, to) };
+        self.requested_snaplen = Some(to);
         self
     }
 }
 
+/// The `try_*` builders behave like the builders above, but return the capture along with an
+/// error if libpcap rejects the setting, rather than ignoring it.
+#[allow(clippy::result_large_err)]
+impl Capture<Inactive> {
+    /// Like `timeout()`, but returns an error if libpcap rejects the setting.
+    pub fn try_timeout(self, ms: i32) -> Result<Capture<Inactive>, (Capture<Inactive>, Error)> {
+        let status = unsafe { raw::pcap_set_timeout(*self.handle, ms) };
+        self.check_setting(status)
+    }
+
+    /// Like `tstamp_type()`, but returns an error if libpcap rejects the setting, e.g. because
+    /// the device does not support the time stamp type.
+    #[cfg(libpcap_1_2_1)]
+    pub fn try_tstamp_type(self, tstamp_type: TimestampType)
+        -> Result<Capture<Inactive>, (Capture<Inactive>, Error)>
+    {
+        let status = unsafe { raw::pcap_set_tstamp_type(*self.handle, tstamp_type as _) };
+        self.check_setting(status)
+    }
+
+    /// Like `promisc()`, but returns an error if libpcap rejects the setting.
+    pub fn try_promisc(self, to: bool) -> Result<Capture<Inactive>, (Capture<Inactive>, Error)> {
+        let status = unsafe { raw::pcap_set_promisc(*self.handle, to as _) };
+        self.check_setting(status)
+    }
+
+    /// Like `immediate_mode()`, but returns an error if libpcap rejects the setting.
+    #[cfg(libpcap_1_5_0)]
+    pub fn try_immediate_mode(self, to: bool)
+        -> Result<Capture<Inactive>, (Capture<Inactive>, Error)>
+    {
+        let status = unsafe { raw::pcap_set_immediate_mode(*self.handle, to as _) };
+        self.check_setting(status)
+    }
+
+    /// Like `rfmon()`, but returns an error if libpcap rejects the setting.
+    #[cfg(not(windows))]
+    pub fn try_rfmon(self, to: bool) -> Result<Capture<Inactive>, (Capture<Inactive>, Error)> {
+        let status = unsafe { raw::pcap_set_rfmon(*self.handle, to as _) };
+        self.check_setting(status)
+    }
+
+    /// Like `buffer_size()`, but returns an error if libpcap rejects the setting.
+    pub fn try_buffer_size(self, to: i32) -> Result<Capture<Inactive>, (Capture<Inactive>, Error)> {
+        let status = unsafe { raw::pcap_set_buffer_size(*self.handle, to) };
+        self.check_setting(status)
+    }
+
+    /// Like `precision()`, but returns an error if libpcap rejects the setting, e.g. because
+    /// the device does not support the precision.
+    #[cfg(libpcap_1_5_0)]
+    pub fn try_precision(mut self, precision: Precision)
+        -> Result<Capture<Inactive>, (Capture<Inactive>, Error)>
+    {
+        let status = unsafe { raw::pcap_set_tstamp_precision(*self.handle, precision as _) };
+        self.requested_precision = Some(precision);
+        self.check_setting(status)
+    }
+
+    /// Like `snaplen()`, but returns an error if libpcap rejects the setting.
+    pub fn try_snaplen(mut self, to: i32) -> Result<Capture<Inactive>, (Capture<Inactive>, Error)> {
+        let status = unsafe { raw::pcap_set_snaplen(*self.handle, to) };
+        self.requested_snaplen = Some(to);
+        self.check_setting(status)
+    }
+
+    /// Converts the status returned by a `pcap_set_*` function into the result of a `try_*`
+    /// builder. These functions do not set an error message, so the status is described.
+    fn check_setting(self, status: i32) -> Result<Capture<Inactive>, (Capture<Inactive>, Error)> {
+        if status == 0 {
+            Ok(self)
+        } else {
+            Err((self, PcapError(status_to_str(status))))
+        }
+    }
+}
+
 ///# Activated captures include `Capture<Active>` and `Capture<Offline>`.
 impl<T: Activated + ? Sized> Capture<T> {
     /// List the datalink types that this captured device supports.
+    ///
+    /// Some sources, such as certain offline handles, fail to list their datalink types. The
+    /// current datalink type from `get_datalink()` is then returned as the only one, so that
+    /// there is always at least one; the error is only returned if that is invalid too.
     pub fn list_datalinks(&self) -> Result<Vec<Linktype>, Error> {
-        unsafe {
+        let list = unsafe {
             let mut links: *mut i32 = ptr::null_mut();
             let num = raw::pcap_list_datalinks(*self.handle, &mut links);
             let mut vec = vec![];
@@ -615,27 +1871,177 @@ impl<T: Activated + ? Sized> Capture<T> {
             }
             raw::pcap_free_datalinks(links);
             self.check_err(num > 0).and(Ok(vec))
-        }
+        };
+        datalinks_or_current(list, self.get_datalink())
+    }
+
+    /// List the datalink types that this captured device supports, along with their names and
+    /// descriptions. Link types which libpcap cannot name are skipped; those without a
+    /// description have an empty one.
+    pub fn list_datalinks_described(&self) -> Result<Vec<(Linktype, String, String)>, Error> {
+        Ok(self.list_datalinks()?
+            .into_iter()
+            .filter_map(|linktype| {
+                let name = linktype.get_name().ok()?;
+                let description = linktype.get_description().unwrap_or_default();
+                Some((linktype, name, description))
+            })
+            .collect())
     }
 
     /// Set the datalink type for the current capture handle.
+    ///
+    /// Savefiles created with `savefile()` after this call record the new datalink type in
+    /// their header; savefiles created before it keep the old one. Offline captures only
+    /// accept their existing datalink type, so to convert a savefile to a different link type
+    /// use `savefile_with_linktype()` instead.
+    ///
+    /// A filter installed with `filter()` (or `filter_cached()`) was compiled for the previous
+    /// datalink type, so it is recompiled and reinstalled for the new one. If that fails, the
+    /// previous datalink type is restored and the error is returned. Filters installed with
+    /// `set_filter_raw()` are left as they are.
     pub fn set_datalink(&mut self, linktype: Linktype) -> Result<(), Error> {
-        self.check_err(unsafe { raw::pcap_set_datalink(*self.handle, linktype.0) == 0 })
+        let previous = unsafe { raw::pcap_datalink(*self.handle) };
+        let previous_override = self.linktype_override;
+        let status = unsafe { raw::pcap_set_datalink(*self.handle, linktype.0) };
+        self.datalink.store(DATALINK_UNKNOWN, Ordering::Relaxed);
+        self.check_err(status == 0)?;
+        self.linktype_override = None;
+        // Programs compiled for the previous datalink type are no longer valid
+        self.filter_cache.clear();
+        if let Some(program) = self.filter.clone() {
+            if let Err(e) = self.compile(&program).and_then(|bpf| self.install(&bpf)) {
+                unsafe { raw::pcap_set_datalink(*self.handle, previous) };
+                self.linktype_override = previous_override;
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Sets the datalink type until the returned guard is dropped, when the previous one is
+    /// restored (along with an override from `from_file_with_linktype()`). The capture can be
+    /// used through the guard in the meantime.
+    pub fn with_datalink(&mut self, linktype: Linktype) -> Result<DatalinkGuard<'_, T>, Error> {
+        let previous = unsafe { Linktype(raw::pcap_datalink(*self.handle)) };
+        let previous_override = self.linktype_override;
+        self.set_datalink(linktype)?;
+        Ok(DatalinkGuard { cap: self, previous, previous_override })
+    }
+
+    /// Sets the first datalink type in `preferred` which this capture supports, or failing
+    /// that the first type from `list_datalinks()`, and returns the type which was set.
+    pub fn set_datalink_or_default(&mut self, preferred: &[Linktype]) -> Result<Linktype, Error> {
+        for &linktype in preferred {
+            if self.set_datalink(linktype).is_ok() {
+                return Ok(linktype);
+            }
+        }
+        let linktype = *self.list_datalinks()?.first().ok_or(InvalidLinktype)?;
+        self.set_datalink(linktype)?;
+        Ok(linktype)
     }
 
     /// Get the current datalink type for this capture handle.
+    ///
+    /// The datalink type is cached after the first call, as it only changes with
+    /// `set_datalink()`.
     pub fn get_datalink(&self) -> Linktype {
-        unsafe { Linktype(raw::pcap_datalink(*self.handle)) }
+        if let Some(linktype) = self.linktype_override {
+            return linktype;
+        }
+        match self.datalink.load(Ordering::Relaxed) {
+            DATALINK_UNKNOWN => {
+                let linktype = unsafe { pcap_datalink(*self.handle) };
+                // Negative values are errors, which are not cached
+                if linktype >= 0 {
+                    self.datalink.store(linktype, Ordering::Relaxed);
+                }
+                Linktype(linktype)
+            }
+            linktype => Linktype(linktype),
+        }
+    }
+
+    /// Get the snapshot length (the maximum number of bytes captured from each packet) of this
+    /// capture handle.
+    pub fn get_snaplen(&self) -> i32 {
+        unsafe { raw::pcap_snapshot(*self.handle) }
+    }
+
+    /// Get the time stamp precision of the packets returned by this capture handle.
+    #[cfg(libpcap_1_5_0)]
+    pub fn get_precision(&self) -> Precision {
+        match unsafe { raw::pcap_get_tstamp_precision(*self.handle) } {
+            1 => Precision::Nano,
+            _ => Precision::Micro,
+        }
+    }
+
+    /// Returns `PrecisionMismatch` if a time stamp precision was requested before activation
+    /// and the handle provides a different one.
+    #[cfg(libpcap_1_5_0)]
+    fn check_precision(&self) -> Result<(), Error> {
+        match self.requested_precision {
+            Some(requested) if requested != self.get_precision() => {
+                Err(PrecisionMismatch { requested, actual: self.get_precision() })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns a warning if a snaplen was requested before activation and the handle uses a
+    /// different one.
+    fn check_snaplen(&self) -> Option<Warning> {
+        let requested = self.requested_snaplen?;
+        let actual = self.get_snaplen();
+        if requested == actual {
+            return None;
+        }
+        let message = format!("requested snaplen {} was clamped to {}", requested, actual);
+        Some(Warning::new(raw::PCAP_WARNING, message))
+    }
+
+    /// Returns the timestamp of a packet read from this capture in nanoseconds since the Unix
+    /// epoch, interpreting the sub-second field according to the handle's time stamp
+    /// precision. Timestamps before the epoch are negative.
+    pub fn packet_timestamp_nanos(&self, packet: &Packet) -> i128 {
+        #[cfg(libpcap_1_5_0)]
+        let precision = self.get_precision();
+        #[cfg(not(libpcap_1_5_0))]
+        let precision = Precision::Micro;
+        let subsec_scale = match precision {
+            Precision::Micro => 1_000,
+            Precision::Nano => 1,
+        };
+        let ts = packet.header.ts;
+        ts.tv_sec as i128 * 1_000_000_000 + ts.tv_usec as i128 * subsec_scale
     }
 
     /// Create a `Savefile` context for recording captured packets using this `Capture`'s
-    /// configurations.
+    /// configurations. The file header records the datalink type the handle has at the time
+    /// of this call; an override from `from_file_with_linktype()` is not applied, for which
+    /// `savefile_with_linktype()` can be used instead.
     pub fn savefile<P: AsRef<Path>>(&self, path: P) -> Result<Savefile, Error> {
         let name = CString::new(path.as_ref().to_str().unwrap())?;
         let handle = unsafe { raw::pcap_dump_open(*self.handle, name.as_ptr()) };
         self.check_err(!handle.is_null()).map(|_| Savefile::new(handle))
     }
 
+    /// Create a `Savefile` context like `savefile()`, but recording `linktype` in the file
+    /// header instead of this `Capture`'s current datalink type. The handle itself is left
+    /// unchanged.
+    ///
+    /// Returns `InvalidLinktype` if the link type is not known to libpcap or this crate.
+    pub fn savefile_with_linktype<P: AsRef<Path>>(&self, path: P, linktype: Linktype)
+        -> Result<Savefile, Error>
+    {
+        if linktype.get_name().is_err() && !linktype.is_known() {
+            return Err(InvalidLinktype);
+        }
+        Capture::dead_like(self, linktype)?.savefile(path)
+    }
+
     /// Create a `Savefile` context for recording captured packets using this `Capture`'s
     /// configurations. The output is written to a raw file descriptor which is opened
     /// in `"w"` mode.
@@ -654,16 +2060,97 @@ impl<T: Activated + ? Sized> Capture<T> {
     /// byte order as the host opening the file, and has the same time stamp precision,
     /// link-layer header type,  and  snapshot length as p, it will write new packets
     /// at the end of the file.
+    ///
+    /// The header of an existing file is checked first, and `AppendMismatch` is returned if
+    /// any of these differ.
     #[cfg(libpcap_1_7_2)]
     pub fn savefile_append<P: AsRef<Path>>(&self, path: P) -> Result<Savefile, Error> {
+        self.check_append(path.as_ref())?;
         let name = CString::new(path.as_ref().to_str().unwrap())?;
         let handle = unsafe { raw::pcap_dump_open_append(*self.handle, name.as_ptr()) };
         self.check_err(!handle.is_null()).map(|_| Savefile::new(handle))
     }
 
+    /// Returns `AppendMismatch` if the existing savefile at `path` differs from this capture in
+    /// a way which prevents appending to it. Files which are missing, empty or not pcap files
+    /// are left for pcap_dump_open_append to report.
+    #[cfg(libpcap_1_7_2)]
+    fn check_append(&self, path: &Path) -> Result<(), Error> {
+        use std::io::Read;
+
+        let mut header = Vec::with_capacity(24);
+        match std::fs::File::open(path) {
+            Ok(file) => file.take(24).read_to_end(&mut header)?,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+        if header.len() < 24 {
+            return Ok(());
+        }
+        let mismatch = |field, existing: &dyn fmt::Debug, new: &dyn fmt::Debug| {
+            Err(AppendMismatch {
+                field,
+                existing: format!("{:?}", existing),
+                new: format!("{:?}", new),
+            })
+        };
+
+        let magic = u32::from_ne_bytes([header[0], header[1], header[2], header[3]]);
+        let (swapped, precision) = match magic {
+            0xa1b2_c3d4 => (false, Precision::Micro),
+            0xa1b2_3c4d => (false, Precision::Nano),
+            0xd4c3_b2a1 => (true, Precision::Micro),
+            0x4d3c_b2a1 => (true, Precision::Nano),
+            0x0a0d_0d0a => return mismatch("format", &FileFormat::PcapNg, &FileFormat::Pcap {
+                nanos: self.get_precision() == Precision::Nano,
+            }),
+            _ => return Ok(()),
+        };
+        if swapped {
+            let (existing, new) = if cfg!(target_endian = "little") {
+                ("big-endian", "little-endian")
+            } else {
+                ("little-endian", "big-endian")
+            };
+            return Err(AppendMismatch {
+                field: "byte order",
+                existing: existing.to_string(),
+                new: new.to_string(),
+            });
+        }
+        if precision != self.get_precision() {
+            return mismatch("precision", &precision, &self.get_precision());
+        }
+
+        // Link types are compared as libpcap maps them to DLT_ values. An override from
+        // `from_file_with_linktype()` is ignored, as the savefile is written with the handle's
+        // datalink type, as it is by `savefile()`.
+        let existing = Capture::from_file(path)?;
+        let linktype = unsafe { Linktype(raw::pcap_datalink(*self.handle)) };
+        if existing.get_datalink() != linktype {
+            return mismatch("linktype", &existing.get_datalink(), &linktype);
+        }
+        if existing.get_snaplen() != self.get_snaplen() {
+            return mismatch("snaplen", &existing.get_snaplen(), &self.get_snaplen());
+        }
+        Ok(())
+    }
+
     /// Set the direction of the capture
     pub fn direction(&self, direction: Direction) -> Result<(), Error> {
-        self.check_err(unsafe { raw::pcap_setdirection(*self.handle, direction as u32 as _) == 0 })
+        self.check_err(unsafe { raw::pcap_setdirection(*self.handle, direction as u32 as _) == 0 })?;
+        self.direction.store(direction as u32, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Get the direction of the capture, as last set with `direction()`. This is
+    /// `Direction::InOut` if it has not been set.
+    pub fn get_direction(&self) -> Direction {
+        match self.direction.load(Ordering::Relaxed) {
+            raw::PCAP_D_IN => Direction::In,
+            raw::PCAP_D_OUT => Direction::Out,
+            _ => Direction::InOut,
+        }
     }
 
     /// Blocks until a packet is returned from the capture handle or an error occurs.
@@ -672,35 +2159,132 @@ impl<T: Activated + ? Sized> Capture<T> {
     /// from. This buffer has a finite length, so if the buffer fills completely new
     /// packets will be discarded temporarily. This means that in realtime situations,
     /// you probably want to minimize the time between calls of this next() method.
+    ///
+    /// If a signal interrupts a blocking read, `Interrupted` is returned and the call can
+    /// simply be retried. This is only detected on Linux, Android, macOS, iOS and the BSDs,
+    /// where errno can be cleared before the read.
     #[allow(clippy::should_implement_trait)]
     pub fn next(&mut self) -> Result<Packet, Error> {
+        let (header, packet) = self.next_raw()?;
+        Ok(unsafe { packet_from_raw(header, packet) })
+    }
+
+    /// Like `next()`, but reports the read timeout expiring and the end of a savefile as
+    /// `NextResult` variants rather than as errors, so that only failures (return code -1) are
+    /// returned as `Err`.
+    pub fn next_ex(&mut self) -> Result<NextResult<'_>, Error> {
+        match self.next() {
+            Ok(packet) => Ok(NextResult::Packet(packet)),
+            Err(TimeoutExpired) => Ok(NextResult::Timeout),
+            Err(NoMorePackets) => Ok(NextResult::Eof),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Reads the next packet with pcap_next_ex, returning pointers to its header and data
+    /// which are valid until the next read.
+    fn next_raw(&mut self) -> Result<(*mut raw::pcap_pkthdr, *const libc::c_uchar), Error> {
         unsafe {
             let mut header: *mut raw::pcap_pkthdr = ptr::null_mut();
             let mut packet: *const libc::c_uchar = ptr::null();
+            // errno is only meaningful after a failed read if it was cleared beforehand, as
+            // pcap_next_ex doesn't set it on every failure.
+            let errno_cleared = clear_errno();
             let retcode = raw::pcap_next_ex(*self.handle, &mut header, &mut packet);
-            self.check_err(retcode != -1)?; // -1 => an error occured while reading the packet
-            match retcode {
-                i if i >= 1 => {
-                    // packet was read without issue
-                    Ok(Packet::new(&*(&*header as *const raw::pcap_pkthdr as *const PacketHeader),
-                                   slice::from_raw_parts(packet, (*header).caplen as _)))
+            if retcode == -1 {
+                // an error occured while reading the packet. errno must be checked before
+                // any other call can overwrite it.
+                if errno_cleared && io::Error::last_os_error().raw_os_error() == Some(libc::EINTR) {
+                    return Err(Interrupted);
                 }
-                0 => {
-                    // packets are being read from a live capture and the
-                    // timeout expired
-                    Err(TimeoutExpired)
-                }
-                -2 => {
-                    // packets are being read from a "savefile" and there are no
-                    // more packets to read
-                    Err(NoMorePackets)
-                }
-                _ => {
-                    // libpcap only defines codes >=1, 0, -1, and -2
-                    unreachable!()
+                return Err(Error::new(raw::pcap_geterr(*self.handle)));
+            }
+            match retcode {
+                // packet was read without issue
+                i if i >= 1 => Ok((header, packet)),
+                i => Err(next_ex_error(i)),
+            }
+        }
+    }
+
+    /// Reads packets without returning them until `stop` returns true (it is called before
+    /// each read) or the end of a savefile is reached, and returns the number of packets and
+    /// the number of bytes captured, e.g. for measuring the rate at which packets can be
+    /// ingested. Read timeouts on live captures are not errors; reading continues.
+    pub fn count_until(&mut self, stop: impl Fn() -> bool) -> Result<(u64, u64), Error> {
+        let mut packets = 0;
+        let mut bytes = 0;
+        while !stop() {
+            match self.next_raw() {
+                Ok((header, _)) => {
+                    packets += 1;
+                    bytes += u64::from(unsafe { (*header).caplen });
                 }
+                Err(TimeoutExpired) => {}
+                Err(NoMorePackets) => break,
+                Err(e) => return Err(e),
             }
         }
+        Ok((packets, bytes))
+    }
+
+    /// Advances past up to `n` packets without returning them, e.g. to seek to a packet in a
+    /// savefile. Returns the number of packets skipped, which is less than `n` if the end of
+    /// the savefile is reached or the read timeout expires first.
+    pub fn skip(&mut self, n: usize) -> Result<usize, Error> {
+        for skipped in 0..n {
+            match self.next_raw() {
+                Ok(_) => {}
+                Err(NoMorePackets) | Err(TimeoutExpired) => return Ok(skipped),
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(n)
+    }
+
+    /// Like `next()`, but copies the packet header into `header` and returns only the packet
+    /// data, so that the header is not tied to the borrow of the capture handle's buffer.
+    pub fn next_with_header(&mut self, header: &mut PacketHeader) -> Result<&[u8], Error> {
+        let packet = self.next()?;
+        *header = *packet.header;
+        Ok(packet.data)
+    }
+
+    /// Like `next_owned()`, but copies the packet into an existing `PacketOwned`, reusing the
+    /// allocation of its data when the packet fits. On error `packet` is left unchanged.
+    pub fn recv_into(&mut self, packet: &mut PacketOwned) -> Result<(), Error> {
+        let next = self.next()?;
+        packet.header = *next.header;
+        packet.data.clear();
+        packet.data.extend_from_slice(next.data);
+        Ok(())
+    }
+
+    /// Like `next()`, but copies the packet out of the capture handle's buffer so that it
+    /// can be held across further calls.
+    pub fn next_owned(&mut self) -> Result<PacketOwned, Error> {
+        self.next().map(PacketOwned::from)
+    }
+
+    /// Returns an iterator over the remaining packets as `(header, data)` tuples, copied out of
+    /// the capture handle's buffer. The iterator ends when `next()` returns `NoMorePackets`;
+    /// other errors are yielded.
+    pub fn tuples(&mut self) -> impl Iterator<Item = Result<(PacketHeader, Vec<u8>), Error>> + '_ {
+        std::iter::from_fn(move || match self.next() {
+            Ok(packet) => Some(Ok((*packet.header, packet.data.to_vec()))),
+            Err(NoMorePackets) => None,
+            Err(e) => Some(Err(e)),
+        })
+    }
+
+    /// Like `next()`, but also returns the packet's link-layer payload as computed by
+    /// `Packet::payload` from this capture's datalink type. The payload is `None` for link
+    /// types with variable-length or unsupported headers.
+    pub fn next_parsed(&mut self) -> Result<(Packet<'_>, Option<&[u8]>), Error> {
+        let linktype = self.get_datalink();
+        let packet = self.next()?;
+        let payload = packet.payload(linktype);
+        Ok((packet, payload))
     }
 
     #[cfg(feature = "capture-stream")]
@@ -730,23 +2314,131 @@ impl<T: Activated + ? Sized> Capture<T> {
         }
     }
 
+    /// Returns a file descriptor which can be used with `select()`/`poll()` to wait for
+    /// packets to become available, or `InvalidRawFd` if the platform or device does not
+    /// provide one.
+    #[cfg(not(windows))]
+    pub fn selectable_fd(&self) -> Result<RawFd, Error> {
+        match unsafe { raw::pcap_get_selectable_fd(*self.handle) } {
+            -1 => Err(InvalidRawFd),
+            fd => Ok(fd),
+        }
+    }
+
+    /// Blocks until packets are available to read or `timeout` elapses, by polling the
+    /// selectable file descriptor. Returns whether packets are available. A `timeout` of `None`
+    /// waits indefinitely.
+    ///
+    /// Note that on some platforms packets only become readable once the capture's own read
+    /// timeout expires or its buffer fills, unless immediate mode is enabled.
+    #[cfg(not(windows))]
+    pub fn wait_readable(&self, timeout: Option<Duration>) -> Result<bool, Error> {
+        let mut pollfd = libc::pollfd {
+            fd: self.selectable_fd()?,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        // Timeouts too long to represent (over 24 days) wait indefinitely
+        let timeout = timeout.map_or(-1, |t| libc::c_int::try_from(t.as_millis()).unwrap_or(-1));
+        match unsafe { libc::poll(&mut pollfd, 1, timeout) } {
+            -1 => Err(io::Error::last_os_error().into()),
+            0 => Ok(false),
+            _ => Ok(pollfd.revents & libc::POLLIN != 0),
+        }
+    }
+
     /// Adds a filter to the capture using the given BPF program string. Internally
     /// this is compiled using `pcap_compile()`.
     ///
     /// See http://biot.com/capstats/bpf.html for more information about this syntax.
     pub fn filter(&mut self, program: &str) -> Result<(), Error> {
+        let bpf_program = self.compile(program)?;
+        self.install(&bpf_program)?;
+        self.filter = Some(program.to_owned());
+        self.filter_raw = None;
+        Ok(())
+    }
+
+    /// Returns the expression of the filter installed with `filter()`, `filter_cached()` or
+    /// `filter_from_file()`, or `None` if there is none or it was installed with
+    /// `set_filter_raw()`.
+    pub fn get_filter(&self) -> Option<&str> {
+        self.filter.as_deref()
+    }
+
+    /// Adds a filter read from the file at `path`, like tcpdump's `-F` option. Everything from
+    /// a `#` to the end of its line is a comment, and the remaining lines are joined into a
+    /// single expression.
+    ///
+    /// Returns an `IoError` if the file cannot be read, and a `PcapError` if the expression
+    /// cannot be compiled.
+    pub fn filter_from_file<P: AsRef<Path>>(&mut self, path: P) -> Result<(), Error> {
+        let text = std::fs::read_to_string(path)?;
+        let program = text
+            .lines()
+            .map(|line| line.split('#').next().unwrap_or("").trim())
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+        self.filter(&program)
+    }
+
+    /// Like `filter()`, but keeps the compiled program so that it is only compiled once for
+    /// each distinct `program`. This makes switching between a fixed set of filters cheaper.
+    /// The cache is cleared when the datalink type is changed with `set_datalink()`.
+    pub fn filter_cached(&mut self, program: &str) -> Result<(), Error> {
+        if !self.filter_cache.contains_key(program) {
+            let bpf_program = self.compile(program)?;
+            self.filter_cache.insert(program.to_owned(), bpf_program);
+        }
+        let bpf_program = &self.filter_cache[program];
+        self.install(bpf_program)?;
+        self.filter = Some(program.to_owned());
+        self.filter_raw = None;
+        Ok(())
+    }
+
+    fn compile(&self, program: &str) -> Result<BpfProgram, Error> {
         let program = CString::new(program)?;
         unsafe {
             let mut bpf_program: raw::bpf_program = mem::zeroed();
             let ret = raw::pcap_compile(*self.handle, &mut bpf_program, program.as_ptr(), 0, 0);
+            // The error must be read before any other call on the handle, which could
+            // overwrite the compiler's message in the error buffer.
             self.check_err(ret != -1)?;
-            let ret = raw::pcap_setfilter(*self.handle, &mut bpf_program);
-            raw::pcap_freecode(&mut bpf_program);
-            self.check_err(ret != -1)
+            Ok(BpfProgram(bpf_program))
         }
     }
 
+    fn install(&self, bpf_program: &BpfProgram) -> Result<(), Error> {
+        let mut bpf_program = bpf_program.0;
+        self.check_err(unsafe { raw::pcap_setfilter(*self.handle, &mut bpf_program) != -1 })
+    }
+
+    /// Installs a precompiled BPF program as the filter for this capture, bypassing
+    /// `pcap_compile()`. libpcap copies the program, so `insns` need only live for the
+    /// duration of the call.
+    pub fn set_filter_raw(&mut self, insns: &[BpfInstruction]) -> Result<(), Error> {
+        let mut bpf_program = raw::bpf_program {
+            bf_len: insns.len() as _,
+            bf_insns: insns.as_ptr() as *mut raw::bpf_insn,
+        };
+        self.check_err(unsafe { raw::pcap_setfilter(*self.handle, &mut bpf_program) != -1 })?;
+        self.filter = None;
+        self.filter_raw = Some(insns.to_vec());
+        Ok(())
+    }
+
     pub fn stats(&mut self) -> Result<Stat, Error> {
+        self.stats_shared()
+    }
+
+    /// Like `stats()`, but only borrows the capture immutably. Calls are serialised with those
+    /// of other threads and of `StatsHandle`s, as libpcap may update counters kept on the
+    /// handle. To read statistics from another thread while this one is reading packets, use
+    /// `Capture<Active>::stats_handle`.
+    pub fn stats_shared(&self) -> Result<Stat, Error> {
+        let _lock = self.alive.lock().unwrap_or_else(|e| e.into_inner());
         unsafe {
             let mut stats: raw::pcap_stat = mem::zeroed();
             self.check_err(raw::pcap_stats(*self.handle, &mut stats) != -1)
@@ -756,32 +2448,306 @@ impl<T: Activated + ? Sized> Capture<T> {
 }
 
 impl Capture<Active> {
+    /// Puts the capture handle into non-blocking mode, where `next()` returns
+    /// `TimeoutExpired` immediately if no packets are available.
+    pub fn setnonblock(mut self) -> Result<Capture<Active>, Error> {
+        with_errbuf(|err| unsafe {
+            if raw::pcap_setnonblock(*self.handle, 1, err) != 0 {
+                return Err(Error::new(err));
+            }
+            self.nonblock = true;
+            Ok(self)
+        })
+    }
+
+    /// Reads up to `max` packets which are immediately available, without blocking. Reading
+    /// stops early when no more packets are available (`TimeoutExpired` or a `WouldBlock` IO
+    /// error). Returns `NonNonBlock` if the handle is not in non-blocking mode.
+    pub fn drain(&mut self, max: usize) -> Result<Vec<PacketOwned>, Error> {
+        if !self.nonblock {
+            return Err(NonNonBlock);
+        }
+        let mut packets = vec![];
+        while packets.len() < max {
+            match self.next_owned() {
+                Ok(packet) => packets.push(packet),
+                Err(TimeoutExpired) => break,
+                Err(IoError(io::ErrorKind::WouldBlock)) => break,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(packets)
+    }
+
+    /// Like `next()`, but retries up to `max_retries` times when reading fails with an error
+    /// which is likely to be transient, such as a device which is temporarily unavailable
+    /// while a Wi-Fi adapter hops channels, or an interrupted read. Other errors, and the
+    /// read timeout expiring, are returned immediately.
+    pub fn next_retry(&mut self, max_retries: u32) -> Result<Packet<'_>, Error> {
+        let (header, packet) = retry(max_retries, || self.next_raw())?;
+        Ok(unsafe { packet_from_raw(header, packet) })
+    }
+
     /// Sends a packet over this capture handle's interface.
+    ///
+    /// Returns `PacketTooLarge` without attempting to send the packet if it is longer than the
+    /// limit set by `set_max_packet_len()`.
     pub fn sendpacket<B: Borrow<[u8]>>(&mut self, buf: B) -> Result<(), Error> {
         let buf = buf.borrow();
+        if buf.len() > self.max_packet_len {
+            return Err(PacketTooLarge { len: buf.len(), max: self.max_packet_len });
+        }
         self.check_err(unsafe {
             raw::pcap_sendpacket(*self.handle, buf.as_ptr() as _, buf.len() as _) == 0
         })
     }
 
-    pub fn setnonblock(mut self) -> Result<Capture<Active>, Error> {
-        with_errbuf(|err| unsafe {
-            if raw::pcap_setnonblock(*self.handle, 1, err) != 0 {
-                return Err(Error::new(err));
+    /// Sends each packet in `bufs` in turn with `sendpacket()`, returning the number sent.
+    ///
+    /// Sending stops at the first failure, which is returned as `PartialSend` with the number
+    /// of packets sent before it and the underlying error.
+    pub fn send_all<I, B>(&mut self, bufs: I) -> Result<usize, Error>
+    where I: IntoIterator<Item = B>, B: Borrow<[u8]>
+    {
+        let mut sent = 0;
+        for buf in bufs {
+            self.sendpacket(buf).map_err(|error| PartialSend { sent, error: Box::new(error) })?;
+            sent += 1;
+        }
+        Ok(sent)
+    }
+
+    /// Sets the maximum length of packets accepted by `sendpacket()`, which defaults to 65535
+    /// bytes. Raise it to send jumbo frames on interfaces configured for them.
+    pub fn set_max_packet_len(&mut self, max: usize) {
+        self.max_packet_len = max;
+    }
+
+    /// Returns the size of the kernel buffer in use by this capture handle, as reported by
+    /// `pcap_bufsize`. This can be compared against the size requested with `buffer_size()`
+    /// to verify that it took effect.
+    #[cfg(all(windows, libpcap_1_9_0))]
+    pub fn buffer_size(&self) -> i32 {
+        unsafe { raw::pcap_bufsize(*self.handle) }
+    }
+
+    /// Returns the size of the buffer the operating system is actually using for this capture,
+    /// which may be smaller than requested if the system clamps it.
+    ///
+    /// On Windows this is the kernel buffer size reported by `pcap_bufsize`, as requested with
+    /// `buffer_size()`. On Linux it is the capture socket's `SO_RCVBUF`, as requested with
+    /// `set_kernel_rcvbuf()` and clamped to `/proc/sys/net/core/rmem_max`, which the kernel
+    /// reports as double the size set, to allow for bookkeeping overhead. Other platforms
+    /// return `Unsupported`.
+    pub fn effective_buffer_size(&self) -> Result<usize, Error> {
+        #[cfg(all(windows, libpcap_1_9_0))]
+        {
+            Ok(self.buffer_size() as usize)
+        }
+        #[cfg(target_os = "linux")]
+        {
+            let mut size: libc::c_int = 0;
+            let mut len = mem::size_of::<libc::c_int>() as libc::socklen_t;
+            let ret = unsafe {
+                libc::getsockopt(self.as_raw_fd(), libc::SOL_SOCKET, libc::SO_RCVBUF,
+                                 &mut size as *mut libc::c_int as *mut libc::c_void, &mut len)
+            };
+            if ret == -1 {
+                return Err(io::Error::last_os_error().into());
             }
-            self.nonblock = true;
-            Ok(self)
+            Ok(size as usize)
+        }
+        #[cfg(not(any(all(windows, libpcap_1_9_0), target_os = "linux")))]
+        {
+            Err(Unsupported)
+        }
+    }
+
+    /// Returns a handle for reading the statistics of this capture from another thread, e.g.
+    /// for monitoring while this thread is blocked in `next()`.
+    ///
+    /// Only available on Linux, macOS, the BSDs and Windows, where pcap_stats reads the
+    /// counters of live captures from the kernel or driver with a separate call, and only
+    /// updates state on the handle which `next()` does not use. Calls to `stats()` on the
+    /// handle are serialised with each other and with `Capture::stats()`.
+    ///
+    /// The handle may outlive the capture, after which its `stats()` returns
+    /// `CaptureClosed`.
+    #[cfg(any(target_os = "linux", target_os = "macos", target_os = "freebsd",
+              target_os = "netbsd", target_os = "openbsd", target_os = "dragonfly", windows))]
+    pub fn stats_handle(&self) -> stats::StatsHandle {
+        stats::StatsHandle::new(*self.handle, self.alive.clone())
+    }
+
+    /// Sets the receive buffer size (`SO_RCVBUF`) of the capture socket, which bounds how
+    /// many packets the kernel can queue before dropping them. The kernel clamps the size to
+    /// `/proc/sys/net/core/rmem_max`; use `effective_buffer_size()` to read back the result.
+    #[cfg(target_os = "linux")]
+    pub fn set_kernel_rcvbuf(&mut self, bytes: usize) -> Result<(), Error> {
+        let size = libc::c_int::try_from(bytes).map_err(|_| IoError(io::ErrorKind::InvalidInput))?;
+        let ret = unsafe {
+            libc::setsockopt(self.as_raw_fd(), libc::SOL_SOCKET, libc::SO_RCVBUF,
+                             &size as *const libc::c_int as *const libc::c_void,
+                             mem::size_of::<libc::c_int>() as libc::socklen_t)
+        };
+        if ret == -1 {
+            return Err(io::Error::last_os_error().into());
+        }
+        Ok(())
+    }
+
+    /// Reports whether the installed filter is applied in the kernel, by querying the filter
+    /// attached to the capture socket (`SO_GET_FILTER`). libpcap falls back to filtering in
+    /// userspace when it cannot attach a filter to the socket, in which case every packet is
+    /// copied to userspace before being discarded.
+    ///
+    /// Returns `Some(false)` if no filter is attached to the socket, which is also the case
+    /// before any filter is set, and `None` if the kernel does not support the query.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn filter_in_kernel(&self) -> Option<bool> {
+        // SO_ATTACH_FILTER reads the attached filter when passed to getsockopt, which is how
+        // libc defines SO_GET_FILTER where it provides it
+        const SO_GET_FILTER: libc::c_int = libc::SO_ATTACH_FILTER;
+
+        // With no buffer, the number of instructions of the attached filter is returned as its
+        // length
+        let mut len: libc::socklen_t = 0;
+        let ret = unsafe {
+            libc::getsockopt(self.as_raw_fd(), libc::SOL_SOCKET, SO_GET_FILTER,
+                             ptr::null_mut(), &mut len)
+        };
+        if ret == -1 {
+            return None;
+        }
+        Some(len > 0)
+    }
+
+    /// Turns the BPF device's immediate mode on or off with the `BIOCIMMEDIATE` ioctl, so that
+    /// packets are delivered as soon as they arrive rather than when the buffer fills or the
+    /// timeout expires. This is the same as `set_immediate_mode()` on these platforms.
+    #[cfg(any(target_os = "macos", target_os = "freebsd", target_os = "openbsd",
+              target_os = "dragonfly"))]
+    pub fn bsd_set_immediate(&mut self, on: bool) -> Result<(), Error> {
+        self.set_immediate_mode(on)
+    }
+
+    /// Set immediate mode on or off on an activated capture.
+    ///
+    /// On Windows this is implemented with `pcap_setmintocopy` as in `immediate_mode()`, and on
+    /// macOS, FreeBSD, OpenBSD and DragonFly with the BPF device's `BIOCIMMEDIATE` ioctl. Other
+    /// platforms can only enable immediate mode before activation, and return `Unsupported`.
+    pub fn set_immediate_mode(&mut self, to: bool) -> Result<(), Error> {
+        #[cfg(windows)]
+        {
+            let to_copy = if to { 0 } else { raw::WINPCAP_MINTOCOPY_DEFAULT };
+            self.check_err(unsafe { raw::pcap_setmintocopy(*self.handle, to_copy) == 0 })
+        }
+        #[cfg(any(target_os = "macos", target_os = "freebsd", target_os = "openbsd",
+                  target_os = "dragonfly"))]
+        {
+            let mut value: libc::c_uint = if to { 1 } else { 0 };
+            if unsafe { libc::ioctl(self.as_raw_fd(), libc::BIOCIMMEDIATE, &mut value) } == -1 {
+                return Err(io::Error::last_os_error().into());
+            }
+            Ok(())
+        }
+        #[cfg(not(any(windows, target_os = "macos", target_os = "freebsd",
+                      target_os = "openbsd", target_os = "dragonfly")))]
+        {
+            let _ = to;
+            Err(Unsupported)
+        }
+    }
+
+    /// Returns the extended statistics for this capture from `pcap_stats_ex`, which include
+    /// counters beyond those returned by `stats()`. Only available on Windows.
+    #[cfg(windows)]
+    pub fn stats_ex(&mut self) -> Result<StatEx, Error> {
+        let mut size: libc::c_int = 0;
+        let stats = unsafe { raw::pcap_stats_ex(*self.handle, &mut size) };
+        self.check_err(!stats.is_null())?;
+        let stats = unsafe { &*stats };
+        // Older drivers only fill in the leading fields, as indicated by the returned size
+        let available = size.max(0) as usize / mem::size_of::<libc::c_uint>();
+        let field = |index: usize, value: libc::c_uint| if index < available { value } else { 0 };
+        Ok(StatEx {
+            received: field(0, stats.ps_recv),
+            dropped: field(1, stats.ps_drop),
+            if_dropped: field(2, stats.ps_ifdrop),
+            captured: field(3, stats.ps_capt),
+            sent: field(4, stats.ps_sent),
+            net_dropped: field(5, stats.ps_netdrop),
         })
     }
+
+    /// Set the read timeout of an activated capture, in milliseconds.
+    ///
+    /// No released version of libpcap supports this: the timeout can only be changed before
+    /// activation, and the call is rejected with `PCAP_ERROR_ACTIVATED`. In that case
+    /// `Unsupported` is returned and the timeout set with `timeout()` remains in effect. Any
+    /// other failure is returned as a `PcapError`.
+    pub fn set_timeout(&mut self, ms: i32) -> Result<(), Error> {
+        match unsafe { raw::pcap_set_timeout(*self.handle, ms) } {
+            0 => Ok(()),
+            raw::PCAP_ERROR_ACTIVATED => Err(Unsupported),
+            status => Err(self.status_err(status)),
+        }
+    }
+
+    /// Returns the current statistics, as `stats()` does, and records them as the baseline
+    /// for subsequent calls to `stats_since_reset()`.
+    ///
+    /// libpcap's counters are cumulative from when the handle was activated; this allows
+    /// reporting statistics over an interval without tracking the previous values manually.
+    pub fn stats_reset(&mut self) -> Result<Stat, Error> {
+        let stats = self.stats()?;
+        self.stats_baseline = stats;
+        Ok(stats)
+    }
+
+    /// Returns the statistics accumulated since the last call to `stats_reset()`, or since the
+    /// handle was activated if it has never been called.
+    pub fn stats_since_reset(&mut self) -> Result<Stat, Error> {
+        let baseline = self.stats_baseline;
+        self.stats().map(|stats| stats.since(&baseline))
+    }
 }
 
 impl Capture<Dead> {
     /// Creates a "fake" capture handle for the given link type.
+    ///
+    /// Returns `InvalidLinktype` if the link type is not known to libpcap or this crate, as
+    /// savefiles written with it could not be read by other tools.
     pub fn dead(linktype: Linktype) -> Result<Capture<Dead>, Error> {
-        unsafe { raw::pcap_open_dead(linktype.0, 65535).as_mut() }
+        Capture::dead_with_snaplen(linktype, 65535)
+    }
+
+    /// Creates a "fake" capture handle for the given link type and snaplen.
+    ///
+    /// Returns `InvalidLinktype` if the link type is not known to libpcap or this crate.
+    pub fn dead_with_snaplen(linktype: Linktype, snaplen: i32) -> Result<Capture<Dead>, Error> {
+        if linktype.get_name().is_err() && !linktype.is_known() {
+            return Err(InvalidLinktype);
+        }
+        unsafe { raw::pcap_open_dead(linktype.0, snaplen).as_mut() }
         .map(|h| Capture::new(h))
             .ok_or(InsufficientMemory)
     }
+
+    /// Creates a "fake" capture handle with the given link type and the same snaplen and time
+    /// stamp precision as `cap`, for writing savefiles compatible with it.
+    fn dead_like<T: Activated + ?Sized>(cap: &Capture<T>, linktype: Linktype)
+        -> Result<Capture<Dead>, Error>
+    {
+        let snaplen = cap.get_snaplen();
+        #[cfg(libpcap_1_5_0)]
+        let handle = unsafe {
+            raw::pcap_open_dead_with_tstamp_precision(linktype.0, snaplen, cap.get_precision() as _)
+        };
+        #[cfg(not(libpcap_1_5_0))]
+        let handle = unsafe { raw::pcap_open_dead(linktype.0, snaplen) };
+        unsafe { handle.as_mut() }.map(|h| Capture::new(h)).ok_or(InsufficientMemory)
+    }
 }
 
 #[cfg(not(windows))]
@@ -802,7 +2768,42 @@ impl AsRawFd for Capture<Active> {
 
 impl<T: State + ? Sized> Drop for Capture<T> {
     fn drop(&mut self) {
-        unsafe { raw::pcap_close(*self.handle) }
+        self.close_stats_handles();
+        // The handle is null if it was released with `into_raw()`
+        if !self.handle.is_null() {
+            unsafe { raw::pcap_close(*self.handle) }
+        }
+    }
+}
+
+/// Restores the previous datalink type of a capture when dropped, as returned by
+/// `Capture::with_datalink`.
+pub struct DatalinkGuard<'a, T: Activated + ?Sized> {
+    cap: &'a mut Capture<T>,
+    previous: Linktype,
+    previous_override: Option<Linktype>,
+}
+
+impl<'a, T: Activated + ?Sized> Deref for DatalinkGuard<'a, T> {
+    type Target = Capture<T>;
+
+    fn deref(&self) -> &Capture<T> {
+        self.cap
+    }
+}
+
+impl<'a, T: Activated + ?Sized> DerefMut for DatalinkGuard<'a, T> {
+    fn deref_mut(&mut self) -> &mut Capture<T> {
+        self.cap
+    }
+}
+
+impl<'a, T: Activated + ?Sized> Drop for DatalinkGuard<'a, T> {
+    fn drop(&mut self) {
+        // The previous datalink type was in use, so it can only fail to be restored if the
+        // installed filter cannot be recompiled for it, which there is no way to report
+        let _ = self.cap.set_datalink(self.previous);
+        self.cap.linktype_override = self.previous_override;
     }
 }
 
@@ -825,6 +2826,32 @@ impl Savefile {
                            packet.data.as_ptr());
         }
     }
+
+    /// Writes an owned packet, as returned by `Capture::next_owned()`.
+    pub fn write_owned(&mut self, packet: &PacketOwned) {
+        self.write(&packet.as_packet())
+    }
+
+    /// Flushes any packets buffered by the savefile to the underlying file.
+    pub fn flush(&mut self) -> Result<(), Error> {
+        if unsafe { raw::pcap_dump_flush(*self.handle) } == -1 {
+            return Err(io::Error::last_os_error().into());
+        }
+        Ok(())
+    }
+
+    /// Returns the current position in the savefile, i.e. the number of bytes written so far
+    /// including the file header.
+    pub fn tell(&self) -> Result<u64, Error> {
+        #[cfg(libpcap_1_9_0)]
+        let pos = unsafe { raw::pcap_dump_ftell64(*self.handle) };
+        #[cfg(not(libpcap_1_9_0))]
+        let pos = unsafe { raw::pcap_dump_ftell(*self.handle) };
+        if pos == -1 {
+            return Err(io::Error::last_os_error().into());
+        }
+        Ok(pos as u64)
+    }
 }
 
 impl Savefile {
@@ -845,6 +2872,145 @@ pub fn open_raw_fd(fd: RawFd, mode: u8) -> Result<*mut libc::FILE, Error> {
     unsafe { libc::fdopen(fd, mode.as_ptr() as _).as_mut() }.map(|f| f as _).ok_or(InvalidRawFd)
 }
 
+/// Returns libpcap's description of a `PCAP_ERROR_*` or `PCAP_WARNING_*` status code, as
+/// returned by pcap_statustostr.
+pub fn status_to_str(code: i32) -> String {
+    match cstr_to_string(unsafe { raw::pcap_statustostr(code) }) {
+        Ok(Some(msg)) => msg,
+        _ => format!("Unknown error: {}", code),
+    }
+}
+
+/// Returns the version string of the libpcap library in use at runtime, as returned by
+/// pcap_lib_version, e.g. `"libpcap version 1.9.1 (with TPACKET_V3)"`.
+pub fn lib_version() -> String {
+    cstr_to_string(unsafe { raw::pcap_lib_version() })
+        .ok()
+        .and_then(|version| version)
+        .unwrap_or_default()
+}
+
+/// Returns the `(major, minor, patch)` version of the libpcap library in use at runtime, parsed
+/// from `lib_version()`, or `None` if it cannot be parsed. For WinPcap and Npcap, this is the
+/// version of libpcap they are based on.
+pub fn parsed_version() -> Option<(u32, u32, u32)> {
+    parse_lib_version(&lib_version())
+}
+
+#[cfg(test)]
+thread_local! {
+    // `const` initializers are not available in the minimum supported rustc version
+    #[allow(clippy::missing_const_for_thread_local)]
+    static DATALINK_CALLS: std::cell::Cell<usize> = std::cell::Cell::new(0);
+}
+
+/// Calls pcap_datalink for `get_datalink()`, counting the calls in tests so that its caching
+/// can be checked.
+unsafe fn pcap_datalink(handle: *mut raw::pcap_t) -> i32 {
+    #[cfg(test)]
+    DATALINK_CALLS.with(|calls| calls.set(calls.get() + 1));
+    raw::pcap_datalink(handle)
+}
+
+/// Sets errno to 0, returning whether this platform exposes it to do so.
+fn clear_errno() -> bool {
+    #[cfg(any(target_os = "linux", target_os = "emscripten"))]
+    unsafe { *libc::__errno_location() = 0 };
+    #[cfg(any(target_os = "android", target_os = "netbsd", target_os = "openbsd"))]
+    unsafe { *libc::__errno() = 0 };
+    #[cfg(any(target_os = "macos", target_os = "ios", target_os = "freebsd"))]
+    unsafe { *libc::__error() = 0 };
+    cfg!(any(target_os = "linux", target_os = "emscripten", target_os = "android",
+             target_os = "netbsd", target_os = "openbsd", target_os = "macos", target_os = "ios",
+             target_os = "freebsd"))
+}
+
+fn parse_lib_version(version: &str) -> Option<(u32, u32, u32)> {
+    const PREFIX: &str = "libpcap version ";
+    let start = version.find(PREFIX)? + PREFIX.len();
+    let number = version[start..].split(|c: char| !c.is_ascii_digit() && c != '.').next()?;
+    let mut parts = number.split('.').map(|part| part.parse::<u32>());
+    let major = parts.next()?.ok()?;
+    let minor = parts.next()?.ok()?;
+    // Versions such as "1.0 branch 1_0_rel0b" have no patch number
+    let patch = match parts.next() {
+        Some(patch) => patch.ok()?,
+        None => 0,
+    };
+    Some((major, minor, patch))
+}
+
+/// Returns whether the file at `path` starts with the gzip magic number.
+fn is_gzip(path: &Path) -> bool {
+    use std::io::Read;
+
+    let mut magic = [0u8; 2];
+    std::fs::File::open(path).and_then(|mut f| f.read_exact(&mut magic)).is_ok()
+        && magic == [0x1f, 0x8b]
+}
+
+/// Wraps the header and data pointers returned by pcap_next_ex in a `Packet`.
+unsafe fn packet_from_raw<'a>(header: *mut raw::pcap_pkthdr, packet: *const libc::c_uchar)
+    -> Packet<'a>
+{
+    Packet::new(&*(&*header as *const raw::pcap_pkthdr as *const PacketHeader),
+                slice::from_raw_parts(packet, (*header).caplen as _))
+}
+
+/// Calls `read` until it succeeds, fails with an error which `is_transient` does not
+/// consider transient, or has been retried `max_retries` times.
+fn retry<T, F: FnMut() -> Result<T, Error>>(max_retries: u32, mut read: F) -> Result<T, Error> {
+    let mut retries = 0;
+    loop {
+        match read() {
+            Err(ref e) if retries < max_retries && is_transient(e) => retries += 1,
+            result => return result,
+        }
+    }
+}
+
+/// Returns whether a read error from a live capture is likely to go away if retried. libpcap
+/// only reports the `errno` of failed reads in its message.
+fn is_transient(e: &Error) -> bool {
+    const TRANSIENT: &[&str] = &[
+        // EAGAIN, ENOBUFS, ENETDOWN and EINTR
+        "resource temporarily unavailable",
+        "no buffer space available",
+        "network is down",
+        "interrupted system call",
+    ];
+    match *e {
+        Interrupted => true,
+        PcapError(ref msg) => {
+            let msg = msg.to_lowercase();
+            TRANSIENT.iter().any(|transient| msg.contains(transient))
+        }
+        _ => false,
+    }
+}
+
+/// Maps a `pcap_next_ex` return code other than success (>= 1) or error (-1) to an `Error`.
+fn next_ex_error(retcode: i32) -> Error {
+    match retcode {
+        // packets are being read from a live capture and the timeout expired
+        0 => TimeoutExpired,
+        // packets are being read from a "savefile" and there are no more packets to read
+        -2 => NoMorePackets,
+        // libpcap only defines codes >=1, 0, -1, and -2, but don't trust the backend
+        i => PcapError(format!("unexpected pcap_next_ex return code: {}", i)),
+    }
+}
+
+/// Falls back to the current datalink type if listing the datalink types failed.
+fn datalinks_or_current(list: Result<Vec<Linktype>, Error>, current: Linktype)
+    -> Result<Vec<Linktype>, Error>
+{
+    match list {
+        Err(_) if current.0 >= 0 => Ok(vec![current]),
+        list => list,
+    }
+}
+
 #[inline]
 fn cstr_to_string(ptr: *const libc::c_char) -> Result<Option<String>, Error> {
     let string = if ptr.is_null() {
@@ -863,8 +3029,199 @@ where F: FnOnce(*mut libc::c_char) -> Result<T, Error>
     func(errbuf.as_mut_ptr() as _)
 }
 
+#[test]
+#[cfg(libpcap_1_9_0)]
+fn test_remote_auth_marshalling() {
+    let auth = RemoteAuth {
+        auth_type: AuthType::Password,
+        username: Some("user".into()),
+        password: None,
+    };
+    let owned = RawRemoteAuth::new(&auth).unwrap();
+    let raw = owned.as_raw();
+    assert_eq!(raw.type_, 1);
+    assert_eq!(unsafe { CStr::from_ptr(raw.username) }.to_str().unwrap(), "user");
+    assert!(raw.password.is_null());
+
+    let auth = RemoteAuth { username: Some("us\0er".into()), ..auth };
+    assert_eq!(RawRemoteAuth::new(&auth).err().unwrap(), InvalidInputString);
+}
+
+#[test]
+fn test_stat_since() {
+    let baseline = Stat::new(100, 10, 1);
+    assert_eq!(Stat::new(150, 12, 1).since(&baseline), Stat::new(50, 2, 0));
+    assert_eq!(Stat::new(5, 10, 1).since(&Stat::new(u32::MAX - 4, 10, 1)), Stat::new(10, 0, 0));
+}
+
+#[test]
+fn test_datalinks_or_current() {
+    let list = vec![Linktype::ETHERNET, Linktype::IEEE802_11_RADIOTAP];
+    assert_eq!(datalinks_or_current(Ok(list.clone()), Linktype::ETHERNET), Ok(list));
+    let failed = || Err(PcapError("pcap_list_datalinks failed".to_string()));
+    assert_eq!(datalinks_or_current(failed(), Linktype::RAW), Ok(vec![Linktype::RAW]));
+    assert_eq!(datalinks_or_current(failed(), Linktype(-3)), failed());
+}
+
+#[test]
+fn test_retry() {
+    let transient = || PcapError("recvfrom: Network is down".to_string());
+    let mut failures = vec![Ok(1), Err(transient()), Err(Interrupted)];
+    assert_eq!(retry(2, || failures.pop().unwrap()), Ok(1));
+
+    // Giving up after the retries are used
+    let mut failures = vec![Ok(1), Err(transient()), Err(transient())];
+    assert_eq!(retry(1, || failures.pop().unwrap()), Err(transient()));
+
+    // Fatal errors and timeouts are not retried
+    let fatal = || PcapError("The interface disappeared".to_string());
+    let mut failures = vec![Ok(1), Err(fatal())];
+    assert_eq!(retry(5, || failures.pop().unwrap()), Err(fatal()));
+    let mut failures = vec![Ok(1), Err(TimeoutExpired)];
+    assert_eq!(retry(5, || failures.pop().unwrap()), Err(TimeoutExpired));
+}
+
+#[test]
+fn test_next_ex_error() {
+    assert_eq!(next_ex_error(0), TimeoutExpired);
+    assert_eq!(next_ex_error(-2), NoMorePackets);
+    assert_eq!(next_ex_error(-3),
+               PcapError("unexpected pcap_next_ex return code: -3".to_string()));
+}
+
+#[test]
+fn test_warning_message() {
+    let message = "eth0: That device doesn't support promiscuous mode".to_string();
+    let warning = Warning::new(raw::PCAP_WARNING_PROMISC_NOTSUP, message.clone());
+    assert_eq!(warning.code, raw::PCAP_WARNING_PROMISC_NOTSUP);
+    assert_eq!(warning.message, message);
+
+    let warning = Warning::new(raw::PCAP_WARNING_TSTAMP_TYPE_NOTSUP, String::new());
+    assert_eq!(warning.message, status_to_str(raw::PCAP_WARNING_TSTAMP_TYPE_NOTSUP));
+}
+
+#[test]
+fn test_device_select() {
+    let device = |name: &str, flags| Device { name: name.to_string(), desc: None, flags };
+    let up = DeviceFlags::UP | DeviceFlags::RUNNING;
+    let devices = vec![
+        device("docker0", DeviceFlags::empty()),
+        device("lo", up | DeviceFlags::LOOPBACK),
+        device("eth0", up),
+        device("eth1", up),
+    ];
+    let select = |devices: &[Device], prefer| Device::select(devices.to_vec(), prefer).unwrap().name;
+
+    assert_eq!(select(&devices, LookupPref::NonLoopbackUp), "eth0");
+    assert_eq!(select(&devices, LookupPref::AnyUp), "lo");
+    assert_eq!(select(&devices, LookupPref::Loopback), "lo");
+
+    // Falling back to any device which is up, then to the first device
+    assert_eq!(select(&devices[..2], LookupPref::NonLoopbackUp), "lo");
+    assert_eq!(select(&devices[2..], LookupPref::Loopback), "eth0");
+    assert_eq!(select(&devices[..1], LookupPref::AnyUp), "docker0");
+    assert_eq!(Device::select(vec![], LookupPref::AnyUp), None);
+}
+
+#[test]
+fn test_activation_err() {
+    // Monitor mode failures on macOS Wi-Fi interfaces are reported with these two codes, and
+    // must stay distinguishable
+    let cap = Capture::from_device("lo").unwrap();
+    assert_eq!(cap.activation_err(raw::PCAP_ERROR_RFMON_NOTSUP), Activation {
+        kind: ActivationError::RfmonNotSupported,
+        message: status_to_str(raw::PCAP_ERROR_RFMON_NOTSUP),
+    });
+    assert_eq!(cap.activation_err(raw::PCAP_ERROR_IFACE_NOT_UP), Activation {
+        kind: ActivationError::InterfaceNotUp,
+        message: status_to_str(raw::PCAP_ERROR_IFACE_NOT_UP),
+    });
+    assert_eq!(cap.activation_err(raw::PCAP_ERROR_NO_SUCH_DEVICE), Activation {
+        kind: ActivationError::NoSuchDevice,
+        message: status_to_str(raw::PCAP_ERROR_NO_SUCH_DEVICE),
+    });
+    assert_eq!(cap.activation_err(raw::PCAP_ERROR),
+               PcapError(status_to_str(raw::PCAP_ERROR)));
+}
+
+#[test]
+#[cfg(libpcap_1_5_0)]
+fn test_check_precision() {
+    let mut cap = Capture::from_file("tests/data/packet_snaplen_65535.pcap").unwrap();
+    assert_eq!(cap.check_precision(), Ok(()));
+    cap.requested_precision = Some(Precision::Micro);
+    assert_eq!(cap.check_precision(), Ok(()));
+    cap.requested_precision = Some(Precision::Nano);
+    assert_eq!(cap.check_precision(),
+               Err(PrecisionMismatch { requested: Precision::Nano, actual: Precision::Micro }));
+}
+
+#[test]
+fn test_filter_cached() {
+    let mut cap = Capture::from_file("tests/data/packet_snaplen_65535.pcap").unwrap();
+    cap.filter_cached("icmp").unwrap();
+    let insns = cap.filter_cache["icmp"].0.bf_insns;
+
+    // Reusing a filter must not compile it again, which would allocate a new program
+    cap.filter_cached("tcp").unwrap();
+    cap.filter_cached("icmp").unwrap();
+    assert_eq!(cap.filter_cache.len(), 2);
+    assert_eq!(cap.filter_cache["icmp"].0.bf_insns, insns);
+    assert!(cap.next().is_ok());
+
+    assert!(cap.filter_cached("not a (valid filter").is_err());
+    assert_eq!(cap.filter_cache.len(), 2);
+
+    cap.set_datalink(Linktype::ETHERNET).unwrap();
+    assert!(cap.filter_cache.is_empty());
+}
+
+#[test]
+fn test_datalink_cached() {
+    let calls = || DATALINK_CALLS.with(|calls| calls.get());
+    let mut cap = Capture::from_file("tests/data/packet_snaplen_65535.pcap").unwrap();
+    let start = calls();
+    assert_eq!(cap.get_datalink(), Linktype::ETHERNET);
+    assert_eq!(cap.get_datalink(), Linktype::ETHERNET);
+    assert_eq!(calls(), start + 1);
+
+    // Setting the datalink type invalidates the cache, even if it fails
+    cap.set_datalink(Linktype::ETHERNET).unwrap();
+    assert_eq!(cap.get_datalink(), Linktype::ETHERNET);
+    assert_eq!(cap.get_datalink(), Linktype::ETHERNET);
+    assert_eq!(calls(), start + 2);
+    assert!(cap.set_datalink(Linktype::RAW).is_err());
+    assert_eq!(cap.get_datalink(), Linktype::ETHERNET);
+    assert_eq!(calls(), start + 3);
+}
+
+#[test]
+fn test_check_snaplen() {
+    let mut cap = Capture::from_file("tests/data/packet_snaplen_65535.pcap").unwrap();
+    assert_eq!(cap.check_snaplen(), None);
+    cap.requested_snaplen = Some(65535);
+    assert_eq!(cap.check_snaplen(), None);
+    cap.requested_snaplen = Some(1_000_000);
+    let warning = cap.check_snaplen().unwrap();
+    assert_eq!(warning.code, raw::PCAP_WARNING);
+    assert_eq!(warning.message, "requested snaplen 1000000 was clamped to 65535");
+}
+
+#[test]
+fn test_parse_lib_version() {
+    assert_eq!(parse_lib_version("libpcap version 1.9.1 (with TPACKET_V3)"), Some((1, 9, 1)));
+    assert_eq!(parse_lib_version("libpcap version 1.10.0-PRE-GIT"), Some((1, 10, 0)));
+    assert_eq!(parse_lib_version("Npcap version 1.10, based on libpcap version 1.9.1"),
+               Some((1, 9, 1)));
+    assert_eq!(parse_lib_version("WinPcap version 4.1.3 (packet.dll version 4.1.0.2980), \
+                                  based on libpcap version 1.0 branch 1_0_rel0b (20091008)"),
+               Some((1, 0, 0)));
+    assert_eq!(parse_lib_version("something else"), None);
+}
+
 #[test]
 fn test_struct_size() {
     use std::mem::size_of;
     assert_eq!(size_of::<PacketHeader>(), size_of::<raw::pcap_pkthdr>());
+    assert_eq!(size_of::<BpfInstruction>(), size_of::<raw::bpf_insn>());
 }
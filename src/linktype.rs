@@ -0,0 +1,224 @@
+//! Constants for the link types registered at <http://www.tcpdump.org/linktypes.html>.
+
+use super::Linktype;
+
+macro_rules! linktypes {
+    ($($name:ident = $value:expr,)*) => {
+        impl Linktype {
+            $(pub const $name: Linktype = Linktype($value);)*
+
+            /// All link types with a constant defined on `Linktype`, in ascending order.
+            const KNOWN: &'static [Linktype] = &[$(Linktype::$name),*];
+        }
+    }
+}
+
+linktypes! {
+    NULL = 0,
+    ETHERNET = 1,
+    AX25 = 3,
+    IEEE802_5 = 6,
+    ARCNET_BSD = 7,
+    SLIP = 8,
+    PPP = 9,
+    FDDI = 10,
+    PPP_HDLC = 50,
+    PPP_ETHER = 51,
+    ATM_RFC1483 = 100,
+    RAW = 101,
+    C_HDLC = 104,
+    IEEE802_11 = 105,
+    FRELAY = 107,
+    LOOP = 108,
+    LINUX_SLL = 113,
+    LTALK = 114,
+    PFLOG = 117,
+    IEEE802_11_PRISM = 119,
+    IP_OVER_FC = 122,
+    SUNATM = 123,
+    IEEE802_11_RADIOTAP = 127,
+    ARCNET_LINUX = 129,
+    APPLE_IP_OVER_IEEE1394 = 138,
+    MTP2_WITH_PHDR = 139,
+    MTP2 = 140,
+    MTP3 = 141,
+    SCCP = 142,
+    DOCSIS = 143,
+    LINUX_IRDA = 144,
+    USER0 = 147,
+    USER1 = 148,
+    USER2 = 149,
+    USER3 = 150,
+    USER4 = 151,
+    USER5 = 152,
+    USER6 = 153,
+    USER7 = 154,
+    USER8 = 155,
+    USER9 = 156,
+    USER10 = 157,
+    USER11 = 158,
+    USER12 = 159,
+    USER13 = 160,
+    USER14 = 161,
+    USER15 = 162,
+    IEEE802_11_AVS = 163,
+    BACNET_MS_TP = 165,
+    PPP_PPPD = 166,
+    GPRS_LLC = 169,
+    GPF_T = 170,
+    GPF_F = 171,
+    LINUX_LAPD = 177,
+    MFR = 182,
+    BLUETOOTH_HCI_H4 = 187,
+    USB_LINUX = 189,
+    PPI = 192,
+    IEEE802_15_4_WITHFCS = 195,
+    SITA = 196,
+    ERF = 197,
+    BLUETOOTH_HCI_H4_WITH_PHDR = 201,
+    AX25_KISS = 202,
+    LAPD = 203,
+    PPP_WITH_DIR = 204,
+    C_HDLC_WITH_DIR = 205,
+    FRELAY_WITH_DIR = 206,
+    LAPB_WITH_DIR = 207,
+    IPMB_LINUX = 209,
+    FLEXRAY = 210,
+    LIN = 212,
+    IEEE802_15_4_NONASK_PHY = 215,
+    USB_LINUX_MMAPPED = 220,
+    FC_2 = 224,
+    FC_2_WITH_FRAME_DELIMS = 225,
+    IPNET = 226,
+    CAN_SOCKETCAN = 227,
+    IPV4 = 228,
+    IPV6 = 229,
+    IEEE802_15_4_NOFCS = 230,
+    DBUS = 231,
+    DVB_CI = 235,
+    MUX27010 = 236,
+    STANAG_5066_D_PDU = 237,
+    NFLOG = 239,
+    NETANALYZER = 240,
+    NETANALYZER_TRANSPARENT = 241,
+    IPOIB = 242,
+    MPEG_2_TS = 243,
+    NG40 = 244,
+    NFC_LLCP = 245,
+    INFINIBAND = 247,
+    SCTP = 248,
+    USBPCAP = 249,
+    RTAC_SERIAL = 250,
+    BLUETOOTH_LE_LL = 251,
+    NETLINK = 253,
+    BLUETOOTH_LINUX_MONITOR = 254,
+    BLUETOOTH_BREDR_BB = 255,
+    BLUETOOTH_LE_LL_WITH_PHDR = 256,
+    PROFIBUS_DL = 257,
+    PKTAP = 258,
+    EPON = 259,
+    IPMI_HPM_2 = 260,
+    ZWAVE_R1_R2 = 261,
+    ZWAVE_R3 = 262,
+    WATTSTOPPER_DLM = 263,
+    ISO_14443 = 264,
+    RDS = 265,
+    USB_DARWIN = 266,
+    SDLC = 268,
+    LORATAP = 270,
+    VSOCK = 271,
+    NORDIC_BLE = 272,
+    DOCSIS31_XRA31 = 273,
+    ETHERNET_MPACKET = 274,
+    DISPLAYPORT_AUX = 275,
+    LINUX_SLL2 = 276,
+    OPENVIZSLA = 278,
+    EBHSCR = 279,
+    VPP_DISPATCH = 280,
+    DSA_TAG_BRCM = 281,
+    DSA_TAG_BRCM_PREPEND = 282,
+    IEEE802_15_4_TAP = 283,
+    DSA_TAG_DSA = 284,
+    DSA_TAG_EDSA = 285,
+    ELEE = 286,
+    Z_WAVE_SERIAL = 287,
+    USB_2_0 = 288,
+    ATSC_ALP = 289,
+}
+
+/// A broad family of link types, as returned by `Linktype::category`, e.g. for grouping them
+/// in user interfaces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LinktypeCategory {
+    Ethernet,
+    /// IEEE 802.11, with or without a radio information header.
+    WiFi,
+    Bluetooth,
+    /// IEEE 802.15.4 low-rate wireless networks, such as Zigbee.
+    Ieee802154,
+    /// GSM and GPRS cellular networks.
+    Cellular,
+    Usb,
+    Can,
+    /// The `USER0` to `USER15` link types reserved for private use.
+    User,
+    Other,
+}
+
+impl Linktype {
+    /// Returns the family this link type belongs to, or `LinktypeCategory::Other` for link
+    /// types which do not belong to one of the families.
+    pub fn category(&self) -> LinktypeCategory {
+        match *self {
+            Linktype::ETHERNET
+            | Linktype::ETHERNET_MPACKET
+            | Linktype::NETANALYZER
+            | Linktype::NETANALYZER_TRANSPARENT
+            | Linktype::DSA_TAG_BRCM
+            | Linktype::DSA_TAG_BRCM_PREPEND
+            | Linktype::DSA_TAG_DSA
+            | Linktype::DSA_TAG_EDSA => LinktypeCategory::Ethernet,
+            Linktype::IEEE802_11
+            | Linktype::IEEE802_11_PRISM
+            | Linktype::IEEE802_11_RADIOTAP
+            | Linktype::IEEE802_11_AVS
+            | Linktype::PPI => LinktypeCategory::WiFi,
+            Linktype::BLUETOOTH_HCI_H4
+            | Linktype::BLUETOOTH_HCI_H4_WITH_PHDR
+            | Linktype::BLUETOOTH_LE_LL
+            | Linktype::BLUETOOTH_LINUX_MONITOR
+            | Linktype::BLUETOOTH_BREDR_BB
+            | Linktype::BLUETOOTH_LE_LL_WITH_PHDR
+            | Linktype::NORDIC_BLE => LinktypeCategory::Bluetooth,
+            Linktype::IEEE802_15_4_WITHFCS
+            | Linktype::IEEE802_15_4_NONASK_PHY
+            | Linktype::IEEE802_15_4_NOFCS
+            | Linktype::IEEE802_15_4_TAP => LinktypeCategory::Ieee802154,
+            Linktype::GPRS_LLC | Linktype::MUX27010 => LinktypeCategory::Cellular,
+            Linktype::USB_LINUX
+            | Linktype::USB_LINUX_MMAPPED
+            | Linktype::USBPCAP
+            | Linktype::USB_DARWIN
+            | Linktype::USB_2_0
+            | Linktype::OPENVIZSLA => LinktypeCategory::Usb,
+            Linktype::CAN_SOCKETCAN => LinktypeCategory::Can,
+            Linktype(147..=162) => LinktypeCategory::User,
+            _ => LinktypeCategory::Other,
+        }
+    }
+
+    /// Returns whether this is one of the link types with a constant defined on `Linktype`.
+    pub fn is_known(&self) -> bool {
+        Linktype::KNOWN.binary_search_by_key(&self.0, |l| l.0).is_ok()
+    }
+
+    /// Returns all link types with a constant defined on `Linktype`, in ascending order.
+    pub fn all_known() -> &'static [Linktype] {
+        Linktype::KNOWN
+    }
+}
+
+#[test]
+fn test_known_sorted() {
+    assert!(Linktype::KNOWN.windows(2).all(|w| w[0].0 < w[1].0));
+}
@@ -0,0 +1,612 @@
+//! Helpers for extracting information from the link-layer headers of captured packets.
+
+use super::{Direction, Linktype, Packet};
+
+pub(crate) const ETHERTYPE_IPV4: u16 = 0x0800;
+pub(crate) const ETHERTYPE_IPV6: u16 = 0x86dd;
+const ETHERTYPE_ARP: u16 = 0x0806;
+
+const IPPROTO_TCP: u8 = 6;
+const IPPROTO_UDP: u8 = 17;
+
+const DNS_PORT: u16 = 53;
+const DNS_HEADER_LEN: usize = 12;
+/// Compression pointers followed in a single name before it is considered malformed, which
+/// guards against pointer loops.
+const DNS_MAX_POINTERS: usize = 16;
+
+/// EtherTypes of 802.1Q VLAN tags and 802.1ad/legacy QinQ service tags.
+const VLAN_ETHERTYPES: [u16; 3] = [0x8100, 0x88a8, 0x9100];
+/// EtherType values below this are 802.3 length fields rather than EtherTypes.
+const ETHERTYPE_MIN: u16 = 0x0600;
+
+/// Size of `struct ieee80211_radiotap_header`, which begins every radiotap header.
+const RADIOTAP_MIN_LEN: usize = 8;
+/// Size of `struct ppi_packetheader`, which begins every PPI header.
+const PPI_MIN_LEN: usize = 8;
+
+/// `sll_pkttype` value of a packet sent by the capturing host.
+const LINUX_SLL_OUTGOING: u16 = 4;
+/// Offsets of `sll2_if_index` and `sll2_pkttype` within the `LINUX_SLL2` header.
+const LINUX_SLL2_IFINDEX_OFFSET: usize = 4;
+const LINUX_SLL2_PKTTYPE_OFFSET: usize = 10;
+
+/// Offsets of the fields of the PKTAP header (`struct pktap_header` in Darwin's
+/// `net/pktap.h`).
+const PKTAP_DLT_OFFSET: usize = 8;
+const PKTAP_IFNAME_OFFSET: usize = 12;
+const PKTAP_IFNAME_LEN: usize = 24;
+const PKTAP_FLAGS_OFFSET: usize = 36;
+const PKTAP_PID_OFFSET: usize = 52;
+const PKTAP_COMM_OFFSET: usize = 56;
+const PKTAP_COMM_LEN: usize = 17;
+const PKTAP_EPID_OFFSET: usize = 84;
+const PKTAP_ECOMM_OFFSET: usize = 88;
+const PTH_FLAG_DIR_IN: u32 = 0x0001;
+const PTH_FLAG_DIR_OUT: u32 = 0x0002;
+
+/// An 802.1Q VLAN tag of an Ethernet frame, as returned by `Packet::vlan_tags`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VlanTag {
+    /// The EtherType identifying the tag: 0x8100 for 802.1Q tags, 0x88a8 (or the
+    /// non-standard 0x9100) for QinQ service tags.
+    pub tpid: u16,
+    /// The priority code point, from 0 to 7.
+    pub priority: u8,
+    /// The drop eligible indicator.
+    pub drop_eligible: bool,
+    /// The VLAN identifier, from 0 to 4095.
+    pub id: u16,
+}
+
+/// The metadata recorded in the PKTAP header of a packet captured on the macOS `pktap`
+/// device, as returned by `Packet::pktap_metadata`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PktapMetadata {
+    /// The length of the PKTAP header, i.e. the offset at which the captured frame begins.
+    pub header_len: usize,
+    /// The link type of the captured frame.
+    pub linktype: Linktype,
+    /// The name of the interface the packet was captured on.
+    pub interface: String,
+    /// The ID of the process which sent or received the packet, or -1 if it is not known.
+    pub pid: i32,
+    /// The name of the process which sent or received the packet.
+    pub process: String,
+    /// The ID of the process on whose behalf the packet was sent or received (e.g. the app
+    /// using a system daemon), or -1 if it is not known.
+    pub effective_pid: i32,
+    /// The name of the process on whose behalf the packet was sent or received.
+    pub effective_process: String,
+}
+
+/// The operation of an ARP packet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArpOperation {
+    Request,
+    Reply,
+    /// Another operation, such as a RARP request or reply.
+    Other(u16),
+}
+
+/// The fields of an ARP packet, as returned by `Packet::arp`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArpInfo<'a> {
+    /// The hardware address space, e.g. 1 for Ethernet.
+    pub hardware_type: u16,
+    /// The EtherType of the protocol whose addresses are resolved, e.g. 0x0800 for IPv4.
+    pub protocol_type: u16,
+    pub operation: ArpOperation,
+    pub sender_hardware_addr: &'a [u8],
+    pub sender_protocol_addr: &'a [u8],
+    pub target_hardware_addr: &'a [u8],
+    pub target_protocol_addr: &'a [u8],
+}
+
+/// The header and question section of a DNS message, as returned by `Packet::dns`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DnsInfo {
+    pub id: u16,
+    /// Whether the message is a response rather than a query.
+    pub is_response: bool,
+    /// The kind of query, 0 for a standard query.
+    pub opcode: u8,
+    /// The response code, 0 for no error.
+    pub rcode: u8,
+    pub questions: Vec<DnsQuestion>,
+}
+
+/// A question of a DNS message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DnsQuestion {
+    /// The queried name, without a trailing dot.
+    pub name: String,
+    /// The queried record type, e.g. 1 for A or 28 for AAAA records.
+    pub qtype: u16,
+    /// The queried class, usually 1 for the Internet.
+    pub qclass: u16,
+}
+
+/// The control flags of a TCP segment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TcpFlags(u8);
+
+impl TcpFlags {
+    pub const FIN: TcpFlags = TcpFlags(0x01);
+    pub const SYN: TcpFlags = TcpFlags(0x02);
+    pub const RST: TcpFlags = TcpFlags(0x04);
+    pub const PSH: TcpFlags = TcpFlags(0x08);
+    pub const ACK: TcpFlags = TcpFlags(0x10);
+    pub const URG: TcpFlags = TcpFlags(0x20);
+    pub const ECE: TcpFlags = TcpFlags(0x40);
+    pub const CWR: TcpFlags = TcpFlags(0x80);
+}
+
+impl_flags!(TcpFlags: u8 { FIN, SYN, RST, PSH, ACK, URG, ECE, CWR });
+
+/// The fields of a TCP header, as returned by `Packet::tcp`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TcpInfo {
+    pub src_port: u16,
+    pub dst_port: u16,
+    pub seq: u32,
+    /// The acknowledgment number, which is only meaningful if `TcpFlags::ACK` is set.
+    pub ack: u32,
+    pub flags: TcpFlags,
+    pub window: u16,
+}
+
+impl<'a> Packet<'a> {
+    /// Returns the data following the link-layer header, for link types whose header has a
+    /// fixed length. Returns `None` for other link types, or if the header is truncated.
+    pub fn payload(&self, linktype: Linktype) -> Option<&'a [u8]> {
+        self.data.get(link_header_len(linktype)?..)
+    }
+
+    /// Returns the EtherType of the network-layer protocol carried by the packet, along with
+    /// the network-layer data itself. For link types which do not carry an EtherType (such as
+    /// `NULL` or `RAW`), it is derived from the address family or IP version.
+    ///
+    /// Returns `None` if the link type is not supported, the protocol cannot be determined or
+    /// the link-layer header is truncated.
+    pub fn network_layer(&self, linktype: Linktype) -> Option<(u16, &'a [u8])> {
+        let payload = self.payload(linktype)?;
+        let ethertype = match linktype {
+            Linktype::ETHERNET => be_u16(self.data, 12)?,
+            Linktype::LINUX_SLL => be_u16(self.data, 14)?,
+            Linktype::LINUX_SLL2 => be_u16(self.data, 0)?,
+            Linktype::NULL | Linktype::LOOP => {
+                // NULL headers are in the byte order of the host which wrote them
+                let family = le_u32(self.data, 0)?;
+                let family = if family > 0xffff { family.swap_bytes() } else { family };
+                match family {
+                    2 => ETHERTYPE_IPV4,
+                    10 | 24 | 28 | 30 => ETHERTYPE_IPV6,
+                    _ => return None,
+                }
+            }
+            Linktype::RAW => match payload.first()? >> 4 {
+                4 => ETHERTYPE_IPV4,
+                6 => ETHERTYPE_IPV6,
+                _ => return None,
+            },
+            Linktype::IPV4 => ETHERTYPE_IPV4,
+            Linktype::IPV6 => ETHERTYPE_IPV6,
+            _ => return None,
+        };
+        Some((ethertype, payload))
+    }
+
+    /// Returns the EtherType of an Ethernet frame, skipping over any VLAN tags to the type of
+    /// the encapsulated protocol. Returns `None` if `linktype` is not `ETHERNET`, the frame is
+    /// truncated, or is an 802.3 frame whose type field holds a length instead.
+    pub fn ethertype(&self, linktype: Linktype) -> Option<u16> {
+        if linktype != Linktype::ETHERNET {
+            return None;
+        }
+        be_u16(self.data, self.ethertype_offset()?)
+    }
+
+    /// Returns the VLAN tags of an Ethernet frame, outermost first. The list is empty for
+    /// untagged frames, and holds the service tag followed by the customer tag for QinQ frames.
+    /// Tags following a truncated tag are not returned.
+    pub fn vlan_tags(&self) -> Vec<VlanTag> {
+        let mut tags = vec![];
+        let mut offset = 12;
+        while let Some(tpid) = be_u16(self.data, offset) {
+            let tci = match be_u16(self.data, offset + 2) {
+                Some(tci) if VLAN_ETHERTYPES.contains(&tpid) => tci,
+                _ => break,
+            };
+            tags.push(VlanTag {
+                tpid,
+                priority: (tci >> 13) as u8,
+                drop_eligible: tci & 0x1000 != 0,
+                id: tci & 0x0fff,
+            });
+            offset += 4;
+        }
+        tags
+    }
+
+    /// Returns the data following the EtherType of an Ethernet frame, after any VLAN tags.
+    /// Returns `None` in the same cases as `ethertype()` for an Ethernet frame.
+    pub fn strip_vlan(&self) -> Option<&'a [u8]> {
+        self.data.get(self.ethertype_offset()? + 2..)
+    }
+
+    /// Returns the offset of the EtherType of the encapsulated protocol in an Ethernet frame.
+    fn ethertype_offset(&self) -> Option<usize> {
+        let mut offset = 12;
+        loop {
+            let ethertype = be_u16(self.data, offset)?;
+            if VLAN_ETHERTYPES.contains(&ethertype) {
+                offset += 4;
+            } else if ethertype >= ETHERTYPE_MIN {
+                return Some(offset);
+            } else {
+                return None;
+            }
+        }
+    }
+
+    /// Returns whether the header checksum of an IPv4 packet is correct. Returns `None` if the
+    /// packet is not an IPv4 packet, or its header is truncated.
+    ///
+    /// Checksums of outgoing packets are often wrong in captures when the NIC computes them
+    /// (checksum offload), since the packet is captured before the NIC fills them in.
+    pub fn ipv4_checksum_ok(&self, linktype: Linktype) -> Option<bool> {
+        let (ethertype, data) = self.network_layer(linktype)?;
+        if ethertype != ETHERTYPE_IPV4 || data.first()? >> 4 != 4 {
+            return None;
+        }
+        let header_len = ((data[0] & 0x0f) as usize) * 4;
+        if header_len < 20 {
+            return None;
+        }
+        Some(checksum_ok(0, data.get(..header_len)?))
+    }
+
+    /// Returns whether the checksum of a TCP segment carried over IPv4 or IPv6 is correct.
+    /// Returns `None` if the packet is not a TCP packet, is a fragment, or is truncated.
+    pub fn tcp_checksum_ok(&self, linktype: Linktype) -> Option<bool> {
+        let (pseudo_header_sum, segment) = self.transport_segment(linktype, IPPROTO_TCP)?;
+        if segment.len() < 20 {
+            return None;
+        }
+        Some(checksum_ok(pseudo_header_sum, segment))
+    }
+
+    /// Returns whether the checksum of a UDP datagram carried over IPv4 or IPv6 is correct.
+    /// Returns `None` if the packet is not a UDP packet, is a fragment, or is truncated.
+    ///
+    /// A zero checksum, which means that the sender did not compute one, is only accepted
+    /// over IPv4, where it is optional.
+    pub fn udp_checksum_ok(&self, linktype: Linktype) -> Option<bool> {
+        let (pseudo_header_sum, segment) = self.transport_segment(linktype, IPPROTO_UDP)?;
+        if segment.len() < 8 {
+            return None;
+        }
+        if segment[6..8] == [0, 0] && self.network_layer(linktype)?.0 == ETHERTYPE_IPV4 {
+            return Some(true);
+        }
+        Some(checksum_ok(pseudo_header_sum, segment))
+    }
+
+    /// Returns the sum of the IP pseudo-header and the transport-layer data of an unfragmented
+    /// IPv4 or IPv6 packet carrying `protocol`, for verifying its checksum.
+    fn transport_segment(&self, linktype: Linktype, protocol: u8) -> Option<(u32, &'a [u8])> {
+        let (ethertype, data) = self.network_layer(linktype)?;
+        match ethertype {
+            ETHERTYPE_IPV4 => {
+                if data.len() < 20 || data[0] >> 4 != 4 || data[9] != protocol {
+                    return None;
+                }
+                // More fragments flag or fragment offset
+                if be_u16(data, 6)? & 0x3fff != 0 {
+                    return None;
+                }
+                let header_len = ((data[0] & 0x0f) as usize) * 4;
+                if header_len < 20 {
+                    return None;
+                }
+                let total_len = be_u16(data, 2)? as usize;
+                // The packet may be followed by link-layer padding
+                let segment = data.get(header_len..total_len)?;
+                let sum = ones_complement_sum(u32::from(protocol) + segment.len() as u32,
+                                              &data[12..20]);
+                Some((sum, segment))
+            }
+            ETHERTYPE_IPV6 => {
+                if data.len() < 40 || data[0] >> 4 != 6 {
+                    return None;
+                }
+                let payload = data.get(40..40 + be_u16(data, 4)? as usize)?;
+                let mut next_header = data[6];
+                let mut offset = 0;
+                // Skip hop-by-hop options, routing and destination options headers
+                while let 0 | 43 | 60 = next_header {
+                    next_header = *payload.get(offset)?;
+                    offset += (*payload.get(offset + 1)? as usize + 1) * 8;
+                }
+                if next_header != protocol {
+                    return None;
+                }
+                let segment = payload.get(offset..)?;
+                let sum = ones_complement_sum(u32::from(protocol) + segment.len() as u32,
+                                              &data[8..40]);
+                Some((sum, segment))
+            }
+            _ => None,
+        }
+    }
+
+    /// Parses the header of a TCP segment carried in an IPv4 or IPv6 packet. Returns `None` if
+    /// the packet is not TCP, or is truncated or fragmented.
+    pub fn tcp(&self, linktype: Linktype) -> Option<TcpInfo> {
+        let (_, segment) = self.transport_segment(linktype, IPPROTO_TCP)?;
+        Some(TcpInfo {
+            src_port: be_u16(segment, 0)?,
+            dst_port: be_u16(segment, 2)?,
+            seq: be_u32(segment, 4)?,
+            ack: be_u32(segment, 8)?,
+            flags: TcpFlags::from_bits_truncate(*segment.get(13)?),
+            window: be_u16(segment, 14)?,
+        })
+    }
+
+    /// Parses the header and questions of a DNS message sent over UDP or TCP to or from port
+    /// 53. Returns `None` if the packet is not a DNS message, or is truncated or fragmented.
+    ///
+    /// Over TCP, only a message which begins at the start of the segment is recognised.
+    pub fn dns(&self, linktype: Linktype) -> Option<DnsInfo> {
+        let message = match self.transport_segment(linktype, IPPROTO_UDP) {
+            Some((_, segment)) if is_dns(segment) => segment.get(8..)?,
+            Some(_) => return None,
+            None => {
+                let (_, segment) = self.transport_segment(linktype, IPPROTO_TCP)?;
+                if !is_dns(segment) {
+                    return None;
+                }
+                let header_len = (*segment.get(12)? >> 4) as usize * 4;
+                // DNS messages over TCP are prefixed with their length
+                segment.get(header_len + 2..)?
+            }
+        };
+        let flags = be_u16(message, 2)?;
+        let mut offset = DNS_HEADER_LEN;
+        let mut questions = vec![];
+        for _ in 0..be_u16(message, 4)? {
+            let (name, end) = dns_name(message, offset)?;
+            questions.push(DnsQuestion {
+                name,
+                qtype: be_u16(message, end)?,
+                qclass: be_u16(message, end + 2)?,
+            });
+            offset = end + 4;
+        }
+        Some(DnsInfo {
+            id: be_u16(message, 0)?,
+            is_response: flags & 0x8000 != 0,
+            opcode: ((flags >> 11) & 0x0f) as u8,
+            rcode: (flags & 0x0f) as u8,
+            questions,
+        })
+    }
+
+    /// Parses the ARP packet following the link-layer header. Returns `None` if the packet is
+    /// not an ARP packet, or is truncated.
+    pub fn arp(&self, linktype: Linktype) -> Option<ArpInfo<'a>> {
+        let (ethertype, data) = self.network_layer(linktype)?;
+        if ethertype != ETHERTYPE_ARP {
+            return None;
+        }
+        let hardware_len = *data.get(4)? as usize;
+        let protocol_len = *data.get(5)? as usize;
+        let operation = match be_u16(data, 6)? {
+            1 => ArpOperation::Request,
+            2 => ArpOperation::Reply,
+            other => ArpOperation::Other(other),
+        };
+        let mut offset = 8;
+        let mut addr = |len: usize| {
+            let addr = data.get(offset..offset + len);
+            offset += len;
+            addr
+        };
+        Some(ArpInfo {
+            hardware_type: be_u16(data, 0)?,
+            protocol_type: be_u16(data, 2)?,
+            operation,
+            sender_hardware_addr: addr(hardware_len)?,
+            sender_protocol_addr: addr(protocol_len)?,
+            target_hardware_addr: addr(hardware_len)?,
+            target_protocol_addr: addr(protocol_len)?,
+        })
+    }
+
+    /// Returns the length of the radiotap header at the start of an `IEEE802_11_RADIOTAP`
+    /// packet, i.e. the offset at which the 802.11 frame begins. Returns `None` if the packet
+    /// is too short to contain the header it describes.
+    pub fn radiotap_header_len(&self) -> Option<usize> {
+        variable_header_len(self.data, RADIOTAP_MIN_LEN)
+    }
+
+    /// Returns the length of the PPI header at the start of a `PPI` packet, i.e. the offset at
+    /// which the encapsulated frame begins. Returns `None` if the packet is too short to
+    /// contain the header it describes.
+    pub fn ppi_header_len(&self) -> Option<usize> {
+        variable_header_len(self.data, PPI_MIN_LEN)
+    }
+
+    /// Returns the metadata from the PKTAP header at the start of a `PKTAP` packet. Returns
+    /// `None` if the packet is too short to contain the header it describes.
+    pub fn pktap_metadata(&self) -> Option<PktapMetadata> {
+        let header_len = le_u32(self.data, 0)? as usize;
+        if header_len < PKTAP_ECOMM_OFFSET + PKTAP_COMM_LEN || header_len > self.data.len() {
+            return None;
+        }
+        Some(PktapMetadata {
+            header_len,
+            linktype: Linktype(le_u32(self.data, PKTAP_DLT_OFFSET)? as i32),
+            interface: c_string(self.data, PKTAP_IFNAME_OFFSET, PKTAP_IFNAME_LEN)?,
+            pid: le_u32(self.data, PKTAP_PID_OFFSET)? as i32,
+            process: c_string(self.data, PKTAP_COMM_OFFSET, PKTAP_COMM_LEN)?,
+            effective_pid: le_u32(self.data, PKTAP_EPID_OFFSET)? as i32,
+            effective_process: c_string(self.data, PKTAP_ECOMM_OFFSET, PKTAP_COMM_LEN)?,
+        })
+    }
+
+    /// Returns the index of the interface the packet was captured on, as recorded in the
+    /// `LINUX_SLL2` header written by the Linux "any" device. Returns `None` for other link
+    /// types or if the header is truncated.
+    pub fn interface_index(&self, linktype: Linktype) -> Option<u32> {
+        match linktype {
+            Linktype::LINUX_SLL2 => be_u32(self.data, LINUX_SLL2_IFINDEX_OFFSET),
+            _ => None,
+        }
+    }
+
+    /// Returns the direction of the packet as recorded in the link-layer pseudo-header, for
+    /// link types which carry it (`LINUX_SLL`, `LINUX_SLL2` and `PKTAP`). Returns `None` for
+    /// other link types or if the header is truncated.
+    pub fn direction(&self, linktype: Linktype) -> Option<Direction> {
+        match linktype {
+            Linktype::LINUX_SLL => match be_u16(self.data, 0)? {
+                LINUX_SLL_OUTGOING => Some(Direction::Out),
+                0..=3 => Some(Direction::In),
+                _ => None,
+            },
+            Linktype::LINUX_SLL2 => match *self.data.get(LINUX_SLL2_PKTTYPE_OFFSET)? as u16 {
+                LINUX_SLL_OUTGOING => Some(Direction::Out),
+                0..=3 => Some(Direction::In),
+                _ => None,
+            },
+            Linktype::PKTAP => {
+                let flags = le_u32(self.data, PKTAP_FLAGS_OFFSET)?;
+                if flags & PTH_FLAG_DIR_IN != 0 {
+                    Some(Direction::In)
+                } else if flags & PTH_FLAG_DIR_OUT != 0 {
+                    Some(Direction::Out)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Reads the little-endian length field shared by the radiotap and PPI headers, which
+/// follows a one byte version and a one byte padding/flags field.
+fn variable_header_len(data: &[u8], min_len: usize) -> Option<usize> {
+    let len = le_u16(data, 2)? as usize;
+    if len < min_len || len > data.len() {
+        return None;
+    }
+    Some(len)
+}
+
+/// Adds `data` to `sum` as a sequence of big-endian 16-bit words, padding an odd final byte
+/// with zero, without folding the carries.
+fn ones_complement_sum(mut sum: u32, data: &[u8]) -> u32 {
+    let mut words = data.chunks_exact(2);
+    for word in &mut words {
+        sum += u32::from(u16::from_be_bytes([word[0], word[1]]));
+    }
+    if let [last] = *words.remainder() {
+        sum += u32::from(last) << 8;
+    }
+    sum
+}
+
+/// Returns whether `data`, including its checksum field, sums to all ones along with `sum`.
+fn checksum_ok(sum: u32, data: &[u8]) -> bool {
+    let mut sum = ones_complement_sum(sum, data);
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    sum == 0xffff
+}
+
+/// Returns the length of the link-layer header for link types where it is fixed.
+fn link_header_len(linktype: Linktype) -> Option<usize> {
+    match linktype {
+        Linktype::ETHERNET => Some(14),
+        Linktype::LINUX_SLL => Some(16),
+        Linktype::LINUX_SLL2 => Some(20),
+        Linktype::NULL | Linktype::LOOP => Some(4),
+        Linktype::RAW | Linktype::IPV4 | Linktype::IPV6 => Some(0),
+        _ => None,
+    }
+}
+
+/// Returns whether a TCP or UDP segment is to or from the DNS port.
+fn is_dns(segment: &[u8]) -> bool {
+    be_u16(segment, 0) == Some(DNS_PORT) || be_u16(segment, 2) == Some(DNS_PORT)
+}
+
+/// Reads the possibly compressed domain name at `offset` in a DNS message, returning it and
+/// the offset following it.
+fn dns_name(message: &[u8], mut offset: usize) -> Option<(String, usize)> {
+    let mut name = String::new();
+    let mut end = None;
+    let mut pointers = 0;
+    loop {
+        let len = *message.get(offset)? as usize;
+        match len & 0xc0 {
+            0x00 if len == 0 => return Some((name, end.unwrap_or(offset + 1))),
+            0x00 => {
+                let label = message.get(offset + 1..offset + 1 + len)?;
+                if !name.is_empty() {
+                    name.push('.');
+                }
+                name.push_str(&String::from_utf8_lossy(label));
+                offset += 1 + len;
+            }
+            0xc0 => {
+                pointers += 1;
+                if pointers > DNS_MAX_POINTERS {
+                    return None;
+                }
+                end = end.or(Some(offset + 2));
+                offset = (be_u16(message, offset)? & 0x3fff) as usize;
+            }
+            // Extended label types
+            _ => return None,
+        }
+    }
+}
+
+#[inline]
+fn be_u16(data: &[u8], offset: usize) -> Option<u16> {
+    let bytes = data.get(offset..offset + 2)?;
+    Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+#[inline]
+fn be_u32(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset + 4)?;
+    Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+#[inline]
+fn le_u16(data: &[u8], offset: usize) -> Option<u16> {
+    let bytes = data.get(offset..offset + 2)?;
+    Some(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+/// PKTAP headers are written in host byte order, which is little-endian on every platform
+/// that produces them.
+#[inline]
+fn le_u32(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset + 4)?;
+    Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// Reads a NUL-padded string from a fixed-size field.
+fn c_string(data: &[u8], offset: usize, len: usize) -> Option<String> {
+    let field = data.get(offset..offset + len)?;
+    let end = field.iter().position(|&b| b == 0).unwrap_or(len);
+    Some(String::from_utf8_lossy(&field[..end]).into_owned())
+}
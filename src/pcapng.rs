@@ -0,0 +1,242 @@
+//! A reader for pcapng savefiles which reports the link type of every packet.
+//!
+//! A pcapng file may contain several sections, each with several interfaces of different link
+//! types, so unlike `Capture<Offline>` the link type can change from one packet to the next.
+
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::Path;
+
+use super::{Error, Linktype, PacketHeader, PacketOwned};
+
+const BLOCK_SECTION_HEADER: u32 = 0x0a0d_0d0a;
+const BLOCK_INTERFACE_DESCRIPTION: u32 = 0x0000_0001;
+const BLOCK_PACKET: u32 = 0x0000_0002;
+const BLOCK_SIMPLE_PACKET: u32 = 0x0000_0003;
+const BLOCK_ENHANCED_PACKET: u32 = 0x0000_0006;
+
+const BYTE_ORDER_MAGIC: u32 = 0x1a2b_3c4d;
+const OPTION_END: u16 = 0;
+const OPTION_IF_TSRESOL: u16 = 9;
+
+/// Blocks larger than this are rejected rather than read into memory.
+const MAX_BLOCK_LEN: usize = 16 * 1024 * 1024;
+
+/// An interface described by an Interface Description Block.
+#[derive(Debug, Clone, Copy)]
+struct Interface {
+    linktype: Linktype,
+    snaplen: u32,
+    /// Time stamp units per second, from the `if_tsresol` option.
+    ticks_per_sec: u64,
+}
+
+/// Reads the packets of a pcapng savefile along with the link type of the interface each was
+/// captured on, so that consumers can switch parsers when it changes.
+///
+/// Time stamps are converted to microseconds, whatever the resolution of the interface.
+///
+/// # Example
+///
+/// ```ignore
+/// use pcap::pcapng::OfflineNg;
+///
+/// for result in OfflineNg::open("capture.pcapng").unwrap() {
+///     let (linktype, packet) = result.unwrap();
+///     println!("{:?}: {:?}", linktype, packet);
+/// }
+/// ```
+pub struct OfflineNg<R> {
+    reader: R,
+    big_endian: bool,
+    interfaces: Vec<Interface>,
+}
+
+impl OfflineNg<BufReader<File>> {
+    /// Opens the pcapng savefile at `path`.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<OfflineNg<BufReader<File>>, Error> {
+        OfflineNg::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read> OfflineNg<R> {
+    /// Reads a pcapng savefile from `reader`, which must be positioned at its first Section
+    /// Header Block. Returns a `PcapError` if it is not a pcapng savefile.
+    pub fn new(reader: R) -> Result<OfflineNg<R>, Error> {
+        let mut ng = OfflineNg { reader, big_endian: false, interfaces: vec![] };
+        match ng.read_block()? {
+            Some((BLOCK_SECTION_HEADER, body)) => ng.section_header(&body)?,
+            _ => return Err(malformed("not a pcapng savefile")),
+        }
+        Ok(ng)
+    }
+
+    /// Returns the next packet with the link type of its interface, or `None` at the end of
+    /// the file.
+    pub fn next_packet(&mut self) -> Result<Option<(Linktype, PacketOwned)>, Error> {
+        while let Some((block_type, body)) = self.read_block()? {
+            match block_type {
+                BLOCK_SECTION_HEADER => self.section_header(&body)?,
+                BLOCK_INTERFACE_DESCRIPTION => self.interface_description(&body)?,
+                BLOCK_ENHANCED_PACKET => {
+                    let interface = self.u32_at(&body, 0)?;
+                    let ts = self.ts_at(&body, 4)?;
+                    let caplen = self.u32_at(&body, 12)?;
+                    let len = self.u32_at(&body, 16)?;
+                    return self.packet(interface, ts, caplen, len, &body[20..]).map(Some);
+                }
+                BLOCK_PACKET => {
+                    let interface = u32::from(self.u16_at(&body, 0)?);
+                    let ts = self.ts_at(&body, 4)?;
+                    let caplen = self.u32_at(&body, 12)?;
+                    let len = self.u32_at(&body, 16)?;
+                    return self.packet(interface, ts, caplen, len, &body[20..]).map(Some);
+                }
+                BLOCK_SIMPLE_PACKET => {
+                    // Simple packets belong to the first interface, have no time stamp, and are
+                    // only truncated to its snaplen
+                    let len = self.u32_at(&body, 0)?;
+                    let snaplen = match self.interfaces.first() {
+                        Some(interface) if interface.snaplen != 0 => interface.snaplen,
+                        _ => len,
+                    };
+                    let caplen = len.min(snaplen).min((body.len() - 4) as u32);
+                    return self.packet(0, 0, caplen, len, &body[4..]).map(Some);
+                }
+                // Name resolution, statistics, custom and other blocks
+                _ => {}
+            }
+        }
+        Ok(None)
+    }
+
+    /// Starts a new section, whose byte order has already been determined by `read_block`.
+    fn section_header(&mut self, body: &[u8]) -> Result<(), Error> {
+        if self.u16_at(body, 4)? != 1 {
+            return Err(malformed("unsupported pcapng major version"));
+        }
+        self.interfaces.clear();
+        Ok(())
+    }
+
+    fn interface_description(&mut self, body: &[u8]) -> Result<(), Error> {
+        let mut interface = Interface {
+            linktype: Linktype(i32::from(self.u16_at(body, 0)?)),
+            snaplen: self.u32_at(body, 4)?,
+            ticks_per_sec: 1_000_000,
+        };
+        let mut offset = 8;
+        while offset + 4 <= body.len() {
+            let code = self.u16_at(body, offset)?;
+            let len = self.u16_at(body, offset + 2)? as usize;
+            if code == OPTION_END {
+                break;
+            }
+            let value = body
+                .get(offset + 4..offset + 4 + len)
+                .ok_or_else(|| malformed("truncated option"))?;
+            if code == OPTION_IF_TSRESOL && len == 1 {
+                interface.ticks_per_sec = match value[0] {
+                    exp @ 0..=19 => 10u64.pow(u32::from(exp)),
+                    exp @ 0x80..=0xbf => 1u64 << (exp & 0x7f),
+                    _ => return Err(malformed("unsupported time stamp resolution")),
+                };
+            }
+            // Option values are padded to 32 bits
+            offset += 4 + ((len + 3) & !3);
+        }
+        self.interfaces.push(interface);
+        Ok(())
+    }
+
+    fn packet(&self, interface: u32, ts: u64, caplen: u32, len: u32, data: &[u8])
+        -> Result<(Linktype, PacketOwned), Error>
+    {
+        let interface = self.interfaces
+            .get(interface as usize)
+            .ok_or_else(|| malformed("packet refers to an undescribed interface"))?;
+        let data = data.get(..caplen as usize).ok_or_else(|| malformed("truncated packet"))?;
+        let ticks_per_sec = interface.ticks_per_sec;
+        let usec = u128::from(ts % ticks_per_sec) * 1_000_000 / u128::from(ticks_per_sec);
+        let header = PacketHeader {
+            ts: libc::timeval { tv_sec: (ts / ticks_per_sec) as _, tv_usec: usec as _ },
+            caplen,
+            len,
+        };
+        Ok((interface.linktype, PacketOwned { header, data: data.to_vec() }))
+    }
+
+    /// Reads the next block, returning its type and body, or `None` at the end of the file.
+    /// Switches to the byte order of a Section Header Block.
+    fn read_block(&mut self) -> Result<Option<(u32, Vec<u8>)>, Error> {
+        let mut header = [0u8; 8];
+        match self.reader.read(&mut header[..1])? {
+            0 => return Ok(None),
+            _ => self.reader.read_exact(&mut header[1..]).map_err(truncated)?,
+        }
+        let block_type = self.u32_at(&header, 0)?;
+        let mut body = vec![];
+        if block_type == BLOCK_SECTION_HEADER {
+            // The byte order of the section, including the length of this block, is given by
+            // the magic number which follows
+            body.resize(4, 0);
+            self.reader.read_exact(&mut body).map_err(truncated)?;
+            self.big_endian = match [body[0], body[1], body[2], body[3]] {
+                magic if u32::from_le_bytes(magic) == BYTE_ORDER_MAGIC => false,
+                magic if u32::from_be_bytes(magic) == BYTE_ORDER_MAGIC => true,
+                _ => return Err(malformed("invalid byte order magic")),
+            };
+        }
+        let total_len = self.u32_at(&header, 4)? as usize;
+        if total_len < 12 + body.len() || total_len & 3 != 0 || total_len > MAX_BLOCK_LEN {
+            return Err(malformed("invalid block length"));
+        }
+        let start = body.len();
+        // The body is followed by a copy of the total length, which is not checked
+        body.resize(total_len - 8, 0);
+        self.reader.read_exact(&mut body[start..]).map_err(truncated)?;
+        body.truncate(total_len - 12);
+        Ok(Some((block_type, body)))
+    }
+
+    /// Reads a 64-bit time stamp, stored as its high and low 32 bits.
+    fn ts_at(&self, data: &[u8], offset: usize) -> Result<u64, Error> {
+        let high = u64::from(self.u32_at(data, offset)?);
+        Ok((high << 32) | u64::from(self.u32_at(data, offset + 4)?))
+    }
+
+    fn u16_at(&self, data: &[u8], offset: usize) -> Result<u16, Error> {
+        let bytes = data.get(offset..offset + 2).ok_or_else(|| malformed("truncated block"))?;
+        let bytes = [bytes[0], bytes[1]];
+        Ok(if self.big_endian { u16::from_be_bytes(bytes) } else { u16::from_le_bytes(bytes) })
+    }
+
+    fn u32_at(&self, data: &[u8], offset: usize) -> Result<u32, Error> {
+        let bytes = data.get(offset..offset + 4).ok_or_else(|| malformed("truncated block"))?;
+        let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        Ok(if self.big_endian { u32::from_be_bytes(bytes) } else { u32::from_le_bytes(bytes) })
+    }
+}
+
+impl<R: Read> Iterator for OfflineNg<R> {
+    type Item = Result<(Linktype, PacketOwned), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.next_packet() {
+            Ok(Some(packet)) => Some(Ok(packet)),
+            Ok(None) => None,
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+fn malformed(msg: &str) -> Error {
+    Error::PcapError(format!("malformed pcapng savefile: {}", msg))
+}
+
+fn truncated(e: io::Error) -> Error {
+    match e.kind() {
+        io::ErrorKind::UnexpectedEof => malformed("truncated block"),
+        _ => e.into(),
+    }
+}
@@ -1,7 +1,7 @@
 #![allow(dead_code)]
 #![allow(non_camel_case_types)]
 
-use libc::{c_int, c_uint, c_char, c_uchar, c_ushort, sockaddr, timeval, FILE};
+use libc::{c_int, c_uint, c_char, c_long, c_uchar, c_ushort, sockaddr, timeval, FILE};
 
 #[repr(C)]
 #[derive(Copy, Clone)]
@@ -41,6 +41,23 @@ pub const PCAP_D_INOUT: pcap_direction_t = 0;
 pub const PCAP_D_IN: pcap_direction_t = 1;
 pub const PCAP_D_OUT: pcap_direction_t = 2;
 
+pub const PCAP_ERROR: c_int = -1;
+pub const PCAP_ERROR_BREAK: c_int = -2;
+pub const PCAP_ERROR_NOT_ACTIVATED: c_int = -3;
+pub const PCAP_ERROR_ACTIVATED: c_int = -4;
+pub const PCAP_ERROR_NO_SUCH_DEVICE: c_int = -5;
+pub const PCAP_ERROR_RFMON_NOTSUP: c_int = -6;
+pub const PCAP_ERROR_NOT_RFMON: c_int = -7;
+pub const PCAP_ERROR_PERM_DENIED: c_int = -8;
+pub const PCAP_ERROR_IFACE_NOT_UP: c_int = -9;
+pub const PCAP_ERROR_CANTSET_TSTAMP_TYPE: c_int = -10;
+pub const PCAP_ERROR_PROMISC_PERM_DENIED: c_int = -11;
+pub const PCAP_ERROR_TSTAMP_PRECISION_NOTSUP: c_int = -12;
+
+pub const PCAP_WARNING: c_int = 1;
+pub const PCAP_WARNING_PROMISC_NOTSUP: c_int = 2;
+pub const PCAP_WARNING_TSTAMP_TYPE_NOTSUP: c_int = 3;
+
 #[repr(C)]
 #[derive(Copy, Clone)]
 pub struct pcap_pkthdr {
@@ -77,6 +94,14 @@ pub struct pcap_addr_t {
     pub dstaddr: *mut sockaddr,
 }
 
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct pcap_rmtauth {
+    pub type_: c_int,
+    pub username: *mut c_char,
+    pub password: *mut c_char,
+}
+
 pub type pcap_handler = Option<extern "C" fn(arg1: *mut c_uchar,
                                              arg2: *const pcap_pkthdr,
                                              arg3: *const c_uchar)
@@ -110,10 +135,10 @@ extern "C" {
     pub fn pcap_stats(arg1: *mut pcap_t, arg2: *mut pcap_stat) -> c_int;
     pub fn pcap_setfilter(arg1: *mut pcap_t, arg2: *mut bpf_program) -> c_int;
     pub fn pcap_setdirection(arg1: *mut pcap_t, arg2: pcap_direction_t) -> c_int;
-    // pub fn pcap_getnonblock(arg1: *mut pcap_t, arg2: *mut c_char) -> c_int;
+    pub fn pcap_getnonblock(arg1: *mut pcap_t, arg2: *mut c_char) -> c_int;
     pub fn pcap_setnonblock(arg1: *mut pcap_t, arg2: c_int, arg3: *mut c_char) -> c_int;
     pub fn pcap_sendpacket(arg1: *mut pcap_t, arg2: *const c_uchar, arg3: c_int) -> c_int;
-    // pub fn pcap_statustostr(arg1: c_int) -> *const c_char;
+    pub fn pcap_statustostr(arg1: c_int) -> *const c_char;
     // pub fn pcap_strerror(arg1: c_int) -> *const c_char;
     pub fn pcap_geterr(arg1: *mut pcap_t) -> *mut c_char;
     // pub fn pcap_perror(arg1: *mut pcap_t, arg2: *mut c_char);
@@ -132,22 +157,22 @@ extern "C" {
     // pub fn pcap_datalink_name_to_val(arg1: *const c_char) -> c_int;
     pub fn pcap_datalink_val_to_name(arg1: c_int) -> *const c_char;
     pub fn pcap_datalink_val_to_description(arg1: c_int) -> *const c_char;
-    // pub fn pcap_snapshot(arg1: *mut pcap_t) -> c_int;
+    pub fn pcap_snapshot(arg1: *mut pcap_t) -> c_int;
     // pub fn pcap_is_swapped(arg1: *mut pcap_t) -> c_int;
     // pub fn pcap_major_version(arg1: *mut pcap_t) -> c_int;
     // pub fn pcap_minor_version(arg1: *mut pcap_t) -> c_int;
-    // pub fn pcap_file(arg1: *mut pcap_t) -> *mut FILE;
+    pub fn pcap_file(arg1: *mut pcap_t) -> *mut FILE;
     pub fn pcap_fileno(arg1: *mut pcap_t) -> c_int;
     pub fn pcap_dump_open(arg1: *mut pcap_t, arg2: *const c_char) -> *mut pcap_dumper_t;
     pub fn pcap_dump_fopen(arg1: *mut pcap_t, fp: *mut FILE) -> *mut pcap_dumper_t;
     // pub fn pcap_dump_file(arg1: *mut pcap_dumper_t) -> *mut FILE;
-    // pub fn pcap_dump_ftell(arg1: *mut pcap_dumper_t) -> c_long;
-    // pub fn pcap_dump_flush(arg1: *mut pcap_dumper_t) -> c_int;
+    pub fn pcap_dump_ftell(arg1: *mut pcap_dumper_t) -> c_long;
+    pub fn pcap_dump_flush(arg1: *mut pcap_dumper_t) -> c_int;
     pub fn pcap_dump_close(arg1: *mut pcap_dumper_t);
     pub fn pcap_dump(arg1: *mut c_uchar, arg2: *const pcap_pkthdr, arg3: *const c_uchar);
     pub fn pcap_findalldevs(arg1: *mut *mut pcap_if_t, arg2: *mut c_char) -> c_int;
     pub fn pcap_freealldevs(arg1: *mut pcap_if_t);
-    // pub fn pcap_lib_version() -> *const c_char;
+    pub fn pcap_lib_version() -> *const c_char;
     // pub fn bpf_image(arg1: *const bpf_insn, arg2: c_int) -> *mut c_char;
     // pub fn bpf_dump(arg1: *const bpf_program, arg2: c_int);
     pub fn pcap_get_selectable_fd(arg1: *mut pcap_t) -> c_int;
@@ -167,7 +192,7 @@ extern "C" {
 extern "C" {
     pub fn pcap_fopen_offline_with_tstamp_precision(arg1: *mut FILE, arg2: c_uint,
                                                     arg3: *mut c_char) -> *mut pcap_t;
-    // pub fn pcap_get_tstamp_precision(arg1: *mut pcap_t) -> c_int;
+    pub fn pcap_get_tstamp_precision(arg1: *mut pcap_t) -> c_int;
     pub fn pcap_open_dead_with_tstamp_precision(arg1: c_int, arg2: c_int,
                                                 arg3: c_uint) -> *mut pcap_t;
     pub fn pcap_open_offline_with_tstamp_precision(arg1: *const c_char, arg2: c_uint,
@@ -183,12 +208,14 @@ extern "C" {
 
 #[cfg(libpcap_1_9_0)]
 extern "C" {
-    // pcap_bufsize
+    pub fn pcap_bufsize(arg1: *mut pcap_t) -> c_int;
     // pcap_createsrcstr
-    // pcap_dump_ftell64
-    // pcap_findalldevs_ex
+    pub fn pcap_dump_ftell64(arg1: *mut pcap_dumper_t) -> i64;
+    pub fn pcap_findalldevs_ex(source: *const c_char, auth: *mut pcap_rmtauth,
+                               alldevs: *mut *mut pcap_if_t, errbuf: *mut c_char) -> c_int;
     // pcap_get_required_select_timeout
-    // pcap_open
+    pub fn pcap_open(source: *const c_char, snaplen: c_int, flags: c_int, read_timeout: c_int,
+                     auth: *mut pcap_rmtauth, errbuf: *mut c_char) -> *mut pcap_t;
     // pcap_parsesrcstr
     // pcap_remoteact_accept
     // pcap_remoteact_cleanup
@@ -207,10 +234,23 @@ extern "C" {
 #[link(name = "wpcap")]
 pub const WINPCAP_MINTOCOPY_DEFAULT: c_int = 16000;
 
+#[cfg(windows)]
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct pcap_stat_win32 {
+    pub ps_recv: c_uint,
+    pub ps_drop: c_uint,
+    pub ps_ifdrop: c_uint,
+    pub ps_capt: c_uint,
+    pub ps_sent: c_uint,
+    pub ps_netdrop: c_uint,
+}
+
 #[cfg(windows)]
 #[link(name = "wpcap")]
 extern "C" {
     pub fn pcap_setmintocopy(arg1: *mut pcap_t, arg2: c_int) -> c_int;
+    pub fn pcap_stats_ex(arg1: *mut pcap_t, pcap_stat_size: *mut c_int) -> *mut pcap_stat_win32;
 }
 
 #[cfg(not(windows))]
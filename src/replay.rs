@@ -0,0 +1,149 @@
+//! Replaying savefiles onto an interface at their original pace.
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::{Active, Capture, Error, Offline, PacketOwned};
+
+/// Waits shorter than this are spent spinning rather than sleeping, as sleeps may overshoot by
+/// up to a scheduler tick.
+const SPIN_THRESHOLD: Duration = Duration::from_micros(500);
+
+/// The packets of a savefile, which can be sent with the same inter-packet timing as when
+/// they were captured, on any platform.
+///
+/// # Example
+///
+/// ```ignore
+/// use pcap::replay::ReplayQueue;
+///
+/// let savefile = pcap::Capture::from_file("in.pcap").unwrap();
+/// let mut queue = ReplayQueue::from_offline(savefile).unwrap();
+/// let mut cap = pcap::Capture::from_device("eth0").unwrap().open().unwrap();
+/// // Replay at twice the original speed
+/// queue.transmit(&mut cap, 2.0).unwrap();
+/// ```
+pub struct ReplayQueue {
+    /// Packets with their time stamps in nanoseconds.
+    packets: Vec<(i128, PacketOwned)>,
+}
+
+impl ReplayQueue {
+    /// Reads all remaining packets of `cap` into memory.
+    pub fn from_offline(mut cap: Capture<Offline>) -> Result<ReplayQueue, Error> {
+        let mut packets = vec![];
+        loop {
+            match cap.next() {
+                Ok(packet) => {
+                    let packet = PacketOwned::from(packet);
+                    packets.push((cap.packet_timestamp_nanos(&packet.as_packet()), packet));
+                }
+                Err(Error::NoMorePackets) => break,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(ReplayQueue { packets })
+    }
+
+    /// Returns the number of packets in the queue.
+    pub fn len(&self) -> usize {
+        self.packets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.packets.is_empty()
+    }
+
+    /// Sends every packet with `sendpacket()`, spaced by the differences between their time
+    /// stamps divided by `speed`, and returns the number sent. A `speed` of `f64::INFINITY`
+    /// sends the packets as fast as possible. Packets with a time stamp earlier than the one
+    /// before them are sent immediately.
+    ///
+    /// Sending stops at the first failure, which is returned as `PartialSend` with the number
+    /// of packets sent before it and the underlying error.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `speed` is not positive.
+    pub fn transmit(&mut self, sink: &mut Capture<Active>, speed: f64) -> Result<usize, Error> {
+        self.pace(speed, |data| sink.sendpacket(data))
+    }
+
+    fn pace<F>(&self, speed: f64, mut send: F) -> Result<usize, Error>
+        where F: FnMut(&[u8]) -> Result<(), Error>
+    {
+        assert!(speed > 0.0, "replay speed must be positive");
+        let first_ts = match self.packets.first() {
+            Some(&(ts, _)) => ts,
+            None => return Ok(0),
+        };
+        let start = Instant::now();
+        let mut previous = Duration::from_secs(0);
+        for (sent, (ts, packet)) in self.packets.iter().enumerate() {
+            let elapsed = Duration::from_nanos((ts - first_ts).max(0) as u64).div_f64(speed);
+            let offset = elapsed.max(previous);
+            wait_until(start + offset);
+            previous = offset;
+            send(&packet.data)
+                .map_err(|error| Error::PartialSend { sent, error: Box::new(error) })?;
+        }
+        Ok(self.packets.len())
+    }
+}
+
+/// Sleeps until shortly before `deadline`, then spins until it is reached.
+fn wait_until(deadline: Instant) {
+    loop {
+        let now = Instant::now();
+        if now >= deadline {
+            return;
+        }
+        let remaining = deadline - now;
+        if remaining > SPIN_THRESHOLD {
+            thread::sleep(remaining - SPIN_THRESHOLD);
+        } else {
+            thread::yield_now();
+        }
+    }
+}
+
+#[test]
+fn test_pace() {
+    use super::PacketHeader;
+
+    let packet = |ts: i128, byte| {
+        let timeval = libc::timeval { tv_sec: 0, tv_usec: 0 };
+        let header = PacketHeader { ts: timeval, caplen: 1, len: 1 };
+        (ts, PacketOwned { header, data: vec![byte] })
+    };
+    // 0s, 1s, 3s and a packet out of order, replayed at 100 times the original speed
+    let queue = ReplayQueue {
+        packets: vec![
+            packet(5_000_000_000, 0),
+            packet(6_000_000_000, 1),
+            packet(8_000_000_000, 2),
+            packet(7_000_000_000, 3),
+        ],
+    };
+    let start = Instant::now();
+    let mut sent = vec![];
+    assert_eq!(queue.pace(100.0, |data| {
+        sent.push((data[0], start.elapsed()));
+        Ok(())
+    }).unwrap(), 4);
+
+    // Only lower bounds are checked, as sleeps may overrun by any amount on a loaded machine
+    let expected = [0, 10, 30, 30];
+    for (&(byte, elapsed), (i, &expected)) in sent.iter().zip(expected.iter().enumerate()) {
+        assert_eq!(byte as usize, i);
+        let expected = Duration::from_millis(expected);
+        assert!(elapsed >= expected, "packet {} sent early at {:?}", i, elapsed);
+    }
+    assert!(sent.windows(2).all(|pair| pair[0].1 <= pair[1].1));
+
+    let error = queue.pace(1.0, |_| Err(Error::Unsupported)).unwrap_err();
+    match error {
+        Error::PartialSend { sent: 0, .. } => {}
+        e => panic!("unexpected error {:?}", e),
+    }
+}
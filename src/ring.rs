@@ -0,0 +1,79 @@
+//! A fixed-capacity ring of packet buffers, for long-running captures with bounded memory.
+
+use super::{Activated, Capture, Error, PacketHeader, PacketOwned};
+
+/// A fixed number of `PacketOwned` buffers which are reused for every packet read, keeping the
+/// most recent packets. Once the ring is full each read overwrites the oldest packet, reusing
+/// its allocation unless the new packet is larger than any packet previously stored in it.
+///
+/// # Example
+///
+/// ```ignore
+/// use pcap::ring::PacketRing;
+///
+/// let mut cap = pcap::Capture::from_device("any").unwrap().open().unwrap();
+/// let mut ring = PacketRing::new(1024, 65535);
+/// while let Ok(packet) = ring.read_next(&mut cap) {
+///     println!("{:?}", packet.header);
+/// }
+/// ```
+pub struct PacketRing {
+    slots: Vec<PacketOwned>,
+    /// The slot which the next packet is read into.
+    next: usize,
+    len: usize,
+}
+
+impl PacketRing {
+    /// Creates a ring holding up to `capacity` packets, preallocating `buffer_len` bytes for
+    /// each (usually the snaplen of the capture).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
+    pub fn new(capacity: usize, buffer_len: usize) -> PacketRing {
+        assert!(capacity > 0, "a PacketRing must hold at least one packet");
+        let header = PacketHeader {
+            ts: libc::timeval { tv_sec: 0, tv_usec: 0 },
+            caplen: 0,
+            len: 0,
+        };
+        let slots = (0..capacity)
+            .map(|_| PacketOwned { header, data: Vec::with_capacity(buffer_len) })
+            .collect();
+        PacketRing { slots, next: 0, len: 0 }
+    }
+
+    /// Reads the next packet from `cap` into the slot of the oldest packet, and returns it.
+    /// On error the ring is left unchanged.
+    pub fn read_next<T: Activated + ?Sized>(&mut self, cap: &mut Capture<T>)
+        -> Result<&PacketOwned, Error>
+    {
+        let index = self.next;
+        cap.recv_into(&mut self.slots[index])?;
+        self.next = (index + 1) % self.slots.len();
+        self.len = (self.len + 1).min(self.slots.len());
+        Ok(&self.slots[index])
+    }
+
+    /// Returns the maximum number of packets held.
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Returns the number of packets held, which is the capacity once enough packets have been
+    /// read.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the packets held, from the oldest to the most recent.
+    pub fn iter(&self) -> impl Iterator<Item = &PacketOwned> {
+        let start = (self.next + self.slots.len() - self.len) % self.slots.len();
+        self.slots.iter().cycle().skip(start).take(self.len)
+    }
+}
@@ -0,0 +1,176 @@
+//! Savefiles which are rotated to a new file when they grow too large or too old.
+
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use super::{Activated, Capture, Dead, Error, Packet, Savefile};
+
+/// When a `RotatingSavefile` closes the current file and starts a new one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RotationPolicy {
+    /// Rotate once the current file has reached the given size in bytes.
+    Size(u64),
+    /// Rotate once the given wall-clock time has elapsed since the current file was opened.
+    Interval(Duration),
+}
+
+/// A savefile which transparently rotates to a new file according to a `RotationPolicy`.
+///
+/// File names are generated from a template, in which the following `strftime`-style
+/// placeholders are replaced with the (UTC) time at which the file is opened:
+///
+/// * `%Y`, `%m`, `%d`: year, month and day
+/// * `%H`, `%M`, `%S`: hours, minutes and seconds
+/// * `%s`: seconds since the Unix epoch
+/// * `%n`: the number of files opened before this one, starting from 0
+/// * `%%`: a literal `%`
+///
+/// # Example
+///
+/// ```ignore
+/// use pcap::rotate::{RotatingSavefile, RotationPolicy};
+/// use std::time::Duration;
+///
+/// let mut cap = pcap::Device::lookup().unwrap().open().unwrap();
+/// let policy = RotationPolicy::Interval(Duration::from_secs(3600));
+/// let mut savefile = RotatingSavefile::new(&cap, "capture-%Y%m%d-%H%M%S.pcap", policy).unwrap();
+/// while let Ok(packet) = cap.next() {
+///     savefile.write(&packet).unwrap();
+/// }
+/// ```
+pub struct RotatingSavefile {
+    cap: Capture<Dead>,
+    template: String,
+    policy: RotationPolicy,
+    clock: Box<dyn FnMut() -> SystemTime + Send>,
+    current: Option<(Savefile, SystemTime)>,
+    count: u64,
+}
+
+impl RotatingSavefile {
+    /// Creates a rotating savefile for packets read from `cap`, using its link type, snaplen
+    /// and time stamp precision. The first file is opened when the first packet is written.
+    pub fn new<T, S>(cap: &Capture<T>, template: S, policy: RotationPolicy)
+        -> Result<RotatingSavefile, Error>
+    where T: Activated + ?Sized, S: Into<String>
+    {
+        Ok(RotatingSavefile {
+            cap: Capture::dead_like(cap, cap.get_datalink())?,
+            template: template.into(),
+            policy,
+            clock: Box::new(SystemTime::now),
+            current: None,
+            count: 0,
+        })
+    }
+
+    /// Replaces the clock used to time file rotations and name files, which defaults to
+    /// `SystemTime::now`.
+    pub fn with_clock<F>(mut self, clock: F) -> RotatingSavefile
+    where F: FnMut() -> SystemTime + Send + 'static
+    {
+        self.clock = Box::new(clock);
+        self
+    }
+
+    /// Writes a packet to the current file, first rotating to a new file if the policy
+    /// requires it.
+    pub fn write(&mut self, packet: &Packet) -> Result<(), Error> {
+        let now = (self.clock)();
+        let rotate = match self.current {
+            None => true,
+            Some((ref savefile, opened)) => match self.policy {
+                RotationPolicy::Size(max) => savefile.tell()? >= max,
+                RotationPolicy::Interval(interval) => {
+                    now.duration_since(opened).map(|elapsed| elapsed >= interval).unwrap_or(false)
+                }
+            },
+        };
+        let current = match self.current.take() {
+            Some(current) if !rotate => current,
+            previous => {
+                // Close the previous file before opening its successor, in case they share a name
+                drop(previous);
+                let path = self.path_for(now);
+                let savefile = self.cap.savefile(path)?;
+                self.count += 1;
+                (savefile, now)
+            }
+        };
+        self.current.get_or_insert(current).0.write(packet);
+        Ok(())
+    }
+
+    /// Flushes the current file, if any.
+    pub fn flush(&mut self) -> Result<(), Error> {
+        match self.current {
+            Some((ref mut savefile, _)) => savefile.flush(),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the path of the file which would be opened at `time`.
+    fn path_for(&self, time: SystemTime) -> PathBuf {
+        PathBuf::from(format_template(&self.template, time, self.count))
+    }
+}
+
+/// Expands the placeholders in a file name template.
+fn format_template(template: &str, time: SystemTime, count: u64) -> String {
+    let secs = time.duration_since(UNIX_EPOCH).map(|d| d.as_secs() as i64).unwrap_or(0);
+    let (year, month, day) = civil_from_days(secs.div_euclid(86400));
+    let secs_of_day = secs.rem_euclid(86400);
+
+    let mut result = String::with_capacity(template.len());
+    let mut chars = template.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('Y') => result.push_str(&format!("{:04}", year)),
+            Some('m') => result.push_str(&format!("{:02}", month)),
+            Some('d') => result.push_str(&format!("{:02}", day)),
+            Some('H') => result.push_str(&format!("{:02}", secs_of_day / 3600)),
+            Some('M') => result.push_str(&format!("{:02}", secs_of_day / 60 % 60)),
+            Some('S') => result.push_str(&format!("{:02}", secs_of_day % 60)),
+            Some('s') => result.push_str(&secs.to_string()),
+            Some('n') => result.push_str(&count.to_string()),
+            Some(other) => {
+                result.push('%');
+                if other != '%' {
+                    result.push(other);
+                }
+            }
+            None => result.push('%'),
+        }
+    }
+    result
+}
+
+/// Converts a number of days since 1970-01-01 to a (year, month, day) date in the proleptic
+/// Gregorian calendar.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[test]
+fn test_format_template() {
+    // 2016-04-11 20:58:39 UTC
+    let time = UNIX_EPOCH + Duration::from_secs(1_460_408_319);
+    assert_eq!(format_template("cap-%Y%m%d-%H%M%S.pcap", time, 0), "cap-20160411-205839.pcap");
+    assert_eq!(format_template("%s-%n-100%%", time, 3), "1460408319-3-100%");
+    assert_eq!(format_template("%q%", time, 0), "%q%");
+    assert_eq!(civil_from_days(0), (1970, 1, 1));
+    assert_eq!(civil_from_days(11016), (2000, 2, 29));
+}
@@ -0,0 +1,312 @@
+//! Tracking of capture statistics over time.
+
+use std::collections::VecDeque;
+use std::mem;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use super::{raw, Error, Stat};
+
+/// Packet and drop rates computed by a `StatsTracker`, in events per second.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Rates {
+    pub received_per_sec: f64,
+    pub dropped_per_sec: f64,
+    pub if_dropped_per_sec: f64,
+}
+
+/// Records snapshots of `Capture::stats()` and computes average rates over a sliding window.
+///
+/// The tracker does not read the statistics itself, so it can be driven from any loop or
+/// timer. Snapshots older than the window are discarded, except for the most recent one
+/// preceding it, so that rates are always computed over at least the full window once enough
+/// snapshots have been recorded.
+///
+/// # Example
+///
+/// ```ignore
+/// use pcap::stats::StatsTracker;
+/// use std::time::Duration;
+///
+/// let mut cap = pcap::Device::lookup().unwrap().open().unwrap();
+/// let mut tracker = StatsTracker::new(Duration::from_secs(10));
+/// loop {
+///     std::thread::sleep(Duration::from_secs(1));
+///     tracker.record(cap.stats().unwrap());
+///     println!("{:?}", tracker.rates());
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct StatsTracker {
+    window: Duration,
+    snapshots: VecDeque<(Instant, Stat)>,
+}
+
+impl StatsTracker {
+    /// Creates a tracker which averages rates over `window`.
+    pub fn new(window: Duration) -> StatsTracker {
+        StatsTracker { window, snapshots: VecDeque::new() }
+    }
+
+    /// Records a snapshot taken now.
+    pub fn record(&mut self, stat: Stat) {
+        self.record_at(Instant::now(), stat)
+    }
+
+    /// Records a snapshot taken at `at`. Snapshots must be recorded in chronological order.
+    pub fn record_at(&mut self, at: Instant, stat: Stat) {
+        self.snapshots.push_back((at, stat));
+        if let Some(cutoff) = at.checked_sub(self.window) {
+            while self.snapshots.len() > 2 && self.snapshots[1].0 <= cutoff {
+                self.snapshots.pop_front();
+            }
+        }
+    }
+
+    /// Returns the average rates between the oldest and newest snapshots in the window, or
+    /// zero rates if fewer than two snapshots have been recorded.
+    pub fn rates(&self) -> Rates {
+        let (first, last) = match (self.snapshots.front(), self.snapshots.back()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return Rates::default(),
+        };
+        let elapsed = last.0.duration_since(first.0).as_secs_f64();
+        if elapsed <= 0.0 {
+            return Rates::default();
+        }
+        let delta = last.1.since(&first.1);
+        Rates {
+            received_per_sec: delta.received as f64 / elapsed,
+            dropped_per_sec: delta.dropped as f64 / elapsed,
+            if_dropped_per_sec: delta.if_dropped as f64 / elapsed,
+        }
+    }
+}
+
+/// A setting which a `TuningAdvisor` suggests changing to reduce packet drops.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tuning {
+    /// Reopen the capture with this buffer size, in bytes (see `Capture::buffer_size`).
+    BufferSize(i32),
+    /// Reopen the capture with this snaplen, in bytes (see `Capture::snaplen`), so that the
+    /// buffer holds more packets.
+    Snaplen(i32),
+}
+
+/// Suggests capture settings to reduce packet drops, from periodic `Stat` snapshots.
+///
+/// When the proportion of packets dropped by the kernel between two snapshots exceeds a
+/// threshold, the advisor recommends doubling the buffer size, up to a limit, and then
+/// halving the snaplen, down to a minimum. It does not apply the recommendation: the capture
+/// has to be reopened with the new setting, and a new advisor created for it.
+///
+/// # Example
+///
+/// The advisor is given the buffer size the capture was opened with, since libpcap does not
+/// report it back.
+///
+/// ```no_run
+/// use pcap::stats::TuningAdvisor;
+/// use pcap::{Capture, Device};
+///
+/// let buffer_size = 2 * 1024 * 1024;
+/// let mut cap = Capture::from_device(Device::lookup().unwrap()).unwrap()
+///     .buffer_size(buffer_size)
+///     .open().unwrap();
+/// let mut advisor = TuningAdvisor::new(buffer_size, cap.get_snaplen(), 0.01);
+/// loop {
+///     std::thread::sleep(std::time::Duration::from_secs(1));
+///     advisor.observe(cap.stats().unwrap());
+///     if let Some(tuning) = advisor.recommendation() {
+///         println!("consider {:?}", tuning);
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct TuningAdvisor {
+    buffer_size: i32,
+    snaplen: i32,
+    max_drop_ratio: f64,
+    last: Option<Stat>,
+    drop_ratio: f64,
+}
+
+impl TuningAdvisor {
+    /// The largest buffer size which is recommended, in bytes.
+    pub const MAX_BUFFER_SIZE: i32 = 256 * 1024 * 1024;
+    /// The smallest snaplen which is recommended, which is enough for the headers of most
+    /// packets.
+    pub const MIN_SNAPLEN: i32 = 128;
+
+    /// Creates an advisor for a capture with the given buffer size and snaplen, which
+    /// recommends changes when more than `max_drop_ratio` (e.g. 0.01 for 1%) of the packets
+    /// received between two snapshots were dropped.
+    pub fn new(buffer_size: i32, snaplen: i32, max_drop_ratio: f64) -> TuningAdvisor {
+        TuningAdvisor { buffer_size, snaplen, max_drop_ratio, last: None, drop_ratio: 0.0 }
+    }
+
+    /// Records a snapshot, updating the drop ratio since the previous one.
+    pub fn observe(&mut self, stat: Stat) {
+        if let Some(last) = self.last {
+            let delta = stat.since(&last);
+            self.drop_ratio = if delta.received == 0 {
+                0.0
+            } else {
+                f64::from(delta.dropped) / f64::from(delta.received)
+            };
+        }
+        self.last = Some(stat);
+    }
+
+    /// Returns the proportion of received packets which were dropped between the last two
+    /// snapshots.
+    pub fn drop_ratio(&self) -> f64 {
+        self.drop_ratio
+    }
+
+    /// Returns the suggested change if the drop ratio exceeds the threshold, or `None` if it
+    /// does not, or if both settings have reached their limits.
+    pub fn recommendation(&self) -> Option<Tuning> {
+        if self.drop_ratio <= self.max_drop_ratio {
+            None
+        } else if self.buffer_size < TuningAdvisor::MAX_BUFFER_SIZE {
+            let size = self.buffer_size.max(1).saturating_mul(2);
+            Some(Tuning::BufferSize(size.min(TuningAdvisor::MAX_BUFFER_SIZE)))
+        } else if self.snaplen > TuningAdvisor::MIN_SNAPLEN {
+            Some(Tuning::Snaplen((self.snaplen / 2).max(TuningAdvisor::MIN_SNAPLEN)))
+        } else {
+            None
+        }
+    }
+}
+
+/// A handle for reading the statistics of a live capture from another thread, returned by
+/// `Capture<Active>::stats_handle`.
+///
+/// Calls to `stats()` are serialised with those on the capture itself. Closing the capture
+/// waits for any call in progress to return, and later calls return `CaptureClosed`.
+///
+/// The handle can be sent to another thread, but not shared between threads or cloned; each
+/// monitoring thread should get its own handle from the capture.
+///
+/// # Example
+///
+/// ```ignore
+/// use std::{thread, time::Duration};
+///
+/// let mut cap = pcap::Device::lookup().unwrap().open().unwrap();
+/// let handle = cap.stats_handle();
+/// thread::spawn(move || {
+///     while let Ok(stats) = handle.stats() {
+///         println!("{:?}", stats);
+///         thread::sleep(Duration::from_secs(1));
+///     }
+/// });
+/// while let Ok(packet) = cap.next() {
+///     println!("{:?}", packet);
+/// }
+/// ```
+#[derive(Debug)]
+pub struct StatsHandle {
+    handle: *mut raw::pcap_t,
+    alive: Arc<Mutex<bool>>,
+}
+
+// The handle is only used for pcap_stats, while holding the lock on `alive` which serialises
+// the calls and guards the handle from being closed
+unsafe impl Send for StatsHandle {}
+
+impl StatsHandle {
+    pub(crate) fn new(handle: *mut raw::pcap_t, alive: Arc<Mutex<bool>>) -> StatsHandle {
+        StatsHandle { handle, alive }
+    }
+
+    /// Returns the statistics of the capture, as `Capture::stats()` does, or `CaptureClosed`
+    /// if the capture has been closed.
+    ///
+    /// If libpcap fails to read the statistics, the error does not carry its message: the
+    /// message is in the handle's error buffer, which the capture's own thread may be writing
+    /// to concurrently (e.g. from `next()`), so it is not read from here.
+    pub fn stats(&self) -> Result<Stat, Error> {
+        let alive = self.alive.lock().unwrap_or_else(|e| e.into_inner());
+        if !*alive {
+            return Err(Error::CaptureClosed);
+        }
+        unsafe {
+            let mut stats: raw::pcap_stat = mem::zeroed();
+            if raw::pcap_stats(self.handle, &mut stats) == -1 {
+                return Err(Error::PcapError("failed to read the capture statistics".to_string()));
+            }
+            Ok(Stat::new(stats.ps_recv, stats.ps_drop, stats.ps_ifdrop))
+        }
+    }
+
+    /// Returns whether the capture is still open.
+    pub fn is_alive(&self) -> bool {
+        *self.alive.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[test]
+fn test_stats_tracker_rates() {
+    let start = Instant::now();
+    let at = |secs| start + Duration::from_secs(secs);
+    let stat = |received, dropped| Stat { received, dropped, if_dropped: 0 };
+
+    let mut tracker = StatsTracker::new(Duration::from_secs(10));
+    assert_eq!(tracker.rates(), Rates::default());
+    tracker.record_at(at(0), stat(0, 0));
+    assert_eq!(tracker.rates(), Rates::default());
+
+    tracker.record_at(at(5), stat(500, 10));
+    let rates = tracker.rates();
+    assert_eq!(rates.received_per_sec, 100.0);
+    assert_eq!(rates.dropped_per_sec, 2.0);
+
+    // The snapshot at 0s falls out of the window once the one at 5s is at least 10s old
+    tracker.record_at(at(10), stat(1000, 20));
+    tracker.record_at(at(15), stat(3000, 20));
+    assert_eq!(tracker.snapshots.len(), 3);
+    let rates = tracker.rates();
+    assert_eq!(rates.received_per_sec, 250.0);
+    assert_eq!(rates.dropped_per_sec, 1.0);
+    assert_eq!(rates.if_dropped_per_sec, 0.0);
+}
+
+#[test]
+fn test_stats_tracker_wrapping() {
+    let start = Instant::now();
+    let mut tracker = StatsTracker::new(Duration::from_secs(60));
+    tracker.record_at(start, Stat { received: 0xffff_fff6, dropped: 0, if_dropped: 0 });
+    tracker.record_at(start + Duration::from_secs(2), Stat { received: 10, dropped: 0, if_dropped: 0 });
+    assert_eq!(tracker.rates().received_per_sec, 10.0);
+}
+
+#[test]
+fn test_tuning_advisor() {
+    let stat = |received, dropped| Stat { received, dropped, if_dropped: 0 };
+
+    let mut advisor = TuningAdvisor::new(2 * 1024 * 1024, 65535, 0.01);
+    advisor.observe(stat(0, 0));
+    assert_eq!(advisor.recommendation(), None);
+    // 0.5% drops are within the threshold
+    advisor.observe(stat(1000, 5));
+    assert_eq!(advisor.recommendation(), None);
+    // 10% drops
+    advisor.observe(stat(2000, 105));
+    assert_eq!(advisor.drop_ratio(), 0.1);
+    assert_eq!(advisor.recommendation(), Some(Tuning::BufferSize(4 * 1024 * 1024)));
+    // Drops are measured between snapshots, so they stop being reported once they stop
+    advisor.observe(stat(3000, 105));
+    assert_eq!(advisor.recommendation(), None);
+
+    // Once the buffer is as large as recommended, a smaller snaplen is
+    let mut advisor = TuningAdvisor::new(TuningAdvisor::MAX_BUFFER_SIZE, 200, 0.01);
+    advisor.observe(stat(0, 0));
+    advisor.observe(stat(100, 50));
+    assert_eq!(advisor.recommendation(), Some(Tuning::Snaplen(128)));
+    let mut advisor = TuningAdvisor::new(TuningAdvisor::MAX_BUFFER_SIZE, 128, 0.01);
+    advisor.observe(stat(0, 0));
+    advisor.observe(stat(100, 50));
+    assert_eq!(advisor.recommendation(), None);
+}
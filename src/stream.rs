@@ -7,10 +7,12 @@ use std::marker::Unpin;
 use std::os::unix::io::RawFd;
 use std::pin::Pin;
 use super::Activated;
+use super::Active;
 use super::Packet;
 use super::Error;
 use super::State;
 use super::Capture;
+use super::Stat;
 
 pub struct SelectableFd {
     fd: RawFd
@@ -39,6 +41,14 @@ pub trait PacketCodec {
     fn decode<'a>(&mut self, packet: Packet<'a>) -> Result<Self::Type, Error>;
 }
 
+/// Any closure taking a `Packet` can be used as a codec, e.g. `cap.stream(|p: Packet| p.len())`.
+impl<F, T> PacketCodec for F where F: FnMut(Packet) -> T {
+    type Type = T;
+    fn decode<'a>(&mut self, packet: Packet<'a>) -> Result<T, Error> {
+        Ok(self(packet))
+    }
+}
+
 pub struct PacketStream<T: State + ? Sized, C> {
     cap: Capture<T>,
     fd: tokio::io::PollEvented<SelectableFd>,
@@ -51,6 +61,53 @@ impl<T: Activated + ? Sized, C: PacketCodec> PacketStream<T, C> {
     }
 }
 
+impl<C: PacketCodec> PacketStream<Active, C> {
+    /// Turns this stream into one which also reports packets dropped by the kernel or the
+    /// interface, e.g. because the consumer is not keeping up with the capture.
+    ///
+    /// The capture statistics are checked before every `check_every` packets (or on every
+    /// packet if it is 0), and any increase in dropped packets since the previous check is
+    /// yielded as `StreamItem::Dropped` before the next packet.
+    pub fn report_drops(mut self, check_every: usize) -> Result<DropReportingStream<C>, Error> {
+        let baseline = self.cap.stats()?;
+        Ok(DropReportingStream {
+            inner: self,
+            drops: DropCounter::new(baseline),
+            check_every: check_every.max(1),
+            until_check: 0,
+        })
+    }
+
+    /// Turns this stream into one which reads packets on a separate task into a channel
+    /// holding up to `bound` items, and reports dropped packets along with them as
+    /// `report_drops()` does.
+    ///
+    /// When the consumer lags, the channel fills and the task stops reading from the capture
+    /// until there is room again, so that the kernel buffer fills instead of memory. The packets
+    /// the kernel then drops are yielded as `StreamItem::Dropped` between the packets before
+    /// and after them, once the task has room to send them.
+    ///
+    /// The task is spawned on the current tokio runtime. It stops when the returned stream is
+    /// dropped and it next has an item to send.
+    pub fn buffered(self, bound: usize, check_every: usize)
+        -> Result<BufferedStream<C::Type>, Error>
+    where C: Send + Unpin + 'static, C::Type: Send + 'static
+    {
+        let mut stream = self.report_drops(check_every)?;
+        let (mut sender, receiver) = futures::channel::mpsc::channel(bound);
+        tokio::spawn(async move {
+            use futures::{SinkExt, StreamExt};
+
+            while let Some(item) = stream.next().await {
+                if sender.send(item).await.is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(BufferedStream { receiver })
+    }
+}
+
 impl<'a, T: Activated + ? Sized + Unpin, C: PacketCodec + Unpin> futures::Stream for PacketStream<T, C> {
     type Item = Result<C::Type, Error>;
     fn poll_next(self: Pin<&mut Self>, cx: &mut core::task::Context) -> futures::task::Poll<Option<Self::Item>> {
@@ -66,3 +123,95 @@ impl<'a, T: Activated + ? Sized + Unpin, C: PacketCodec + Unpin> futures::Stream
         futures::task::Poll::Ready(Some(frame_result))
     }
 }
+
+/// An item yielded by a `DropReportingStream`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamItem<T> {
+    /// A packet decoded by the stream's codec.
+    Packet(T),
+    /// The number of packets dropped since the previous report, by the kernel (`Stat::dropped`)
+    /// or the interface (`Stat::if_dropped`).
+    Dropped(u32),
+}
+
+/// A `PacketStream` which reports dropped packets along with the captured ones, returned by
+/// `PacketStream::report_drops`.
+pub struct DropReportingStream<C> {
+    inner: PacketStream<Active, C>,
+    drops: DropCounter,
+    check_every: usize,
+    until_check: usize,
+}
+
+impl<C: PacketCodec + Unpin> futures::Stream for DropReportingStream<C> {
+    type Item = Result<StreamItem<C::Type>, Error>;
+    fn poll_next(self: Pin<&mut Self>, cx: &mut core::task::Context) -> futures::task::Poll<Option<Self::Item>> {
+        let stream = Pin::into_inner(self);
+        if stream.until_check == 0 {
+            let stat = match stream.inner.cap.stats() {
+                Ok(stat) => stat,
+                Err(e) => return futures::task::Poll::Ready(Some(Err(e))),
+            };
+            stream.until_check = stream.check_every;
+            if let Some(dropped) = stream.drops.update(stat) {
+                return futures::task::Poll::Ready(Some(Ok(StreamItem::Dropped(dropped))));
+            }
+        }
+        match Pin::new(&mut stream.inner).poll_next(cx) {
+            futures::task::Poll::Ready(Some(Ok(item))) => {
+                stream.until_check -= 1;
+                futures::task::Poll::Ready(Some(Ok(StreamItem::Packet(item))))
+            }
+            futures::task::Poll::Ready(Some(Err(e))) => futures::task::Poll::Ready(Some(Err(e))),
+            futures::task::Poll::Ready(None) => futures::task::Poll::Ready(None),
+            futures::task::Poll::Pending => futures::task::Poll::Pending,
+        }
+    }
+}
+
+/// A stream of packets read into a bounded channel by a separate task, returned by
+/// `PacketStream::buffered`.
+pub struct BufferedStream<T> {
+    receiver: futures::channel::mpsc::Receiver<Result<StreamItem<T>, Error>>,
+}
+
+impl<T> futures::Stream for BufferedStream<T> {
+    type Item = Result<StreamItem<T>, Error>;
+    fn poll_next(self: Pin<&mut Self>, cx: &mut core::task::Context) -> futures::task::Poll<Option<Self::Item>> {
+        Pin::new(&mut Pin::into_inner(self).receiver).poll_next(cx)
+    }
+}
+
+/// Tracks the number of dropped packets across successive `Stat` snapshots.
+struct DropCounter {
+    last: Stat,
+}
+
+impl DropCounter {
+    fn new(baseline: Stat) -> DropCounter {
+        DropCounter { last: baseline }
+    }
+
+    /// Returns the number of packets dropped since the previous snapshot, if any.
+    fn update(&mut self, stat: Stat) -> Option<u32> {
+        let delta = stat.since(&self.last);
+        self.last = stat;
+        match delta.dropped.wrapping_add(delta.if_dropped) {
+            0 => None,
+            dropped => Some(dropped),
+        }
+    }
+}
+
+#[test]
+fn test_drop_counter() {
+    let mut drops = DropCounter::new(Stat::new(10, 2, 1));
+    // A consumer keeping up with the capture
+    assert_eq!(drops.update(Stat::new(20, 2, 1)), None);
+    // A slow consumer, during which the kernel and the interface dropped packets
+    assert_eq!(drops.update(Stat::new(50, 7, 3)), Some(7));
+    assert_eq!(drops.update(Stat::new(60, 7, 3)), None);
+    // Counters wrapping around
+    let mut drops = DropCounter::new(Stat::new(0, 0xffff_fffe, 0));
+    assert_eq!(drops.update(Stat::new(0, 1, 0)), Some(3));
+}
@@ -0,0 +1,83 @@
+//! Writing savefiles to any `io::Write`, without going through libpcap.
+
+use std::io::Write;
+
+use super::{Error, Linktype, Packet};
+
+const MAGIC: u32 = 0xa1b2_c3d4;
+const VERSION_MAJOR: u16 = 2;
+const VERSION_MINOR: u16 = 4;
+
+/// Writes packets in the classic pcap savefile format to any `io::Write`, such as an in-memory
+/// buffer or a socket, which `Savefile` cannot as libpcap requires a `FILE*`.
+///
+/// Like libpcap, the file is written in the native byte order with microsecond time stamps.
+///
+/// # Example
+///
+/// ```ignore
+/// use pcap::writer::PcapWriter;
+///
+/// let mut cap = pcap::Capture::from_device("eth0").unwrap().open().unwrap();
+/// let mut writer = PcapWriter::new(vec![], cap.get_datalink()).unwrap();
+/// writer.write(&cap.next().unwrap()).unwrap();
+/// let bytes = writer.into_inner();
+/// ```
+#[derive(Debug)]
+pub struct PcapWriter<W: Write> {
+    inner: W,
+}
+
+impl<W: Write> PcapWriter<W> {
+    /// Writes the savefile header for `linktype` with a snaplen of 65535 bytes to `inner`.
+    pub fn new(inner: W, linktype: Linktype) -> Result<PcapWriter<W>, Error> {
+        PcapWriter::with_snaplen(inner, linktype, 65535)
+    }
+
+    /// Writes the savefile header for `linktype` and `snaplen` to `inner`. The snaplen is only
+    /// recorded in the header; packets are written as they are.
+    pub fn with_snaplen(mut inner: W, linktype: Linktype, snaplen: u32)
+        -> Result<PcapWriter<W>, Error>
+    {
+        let mut header = Vec::with_capacity(24);
+        header.extend_from_slice(&MAGIC.to_ne_bytes());
+        header.extend_from_slice(&VERSION_MAJOR.to_ne_bytes());
+        header.extend_from_slice(&VERSION_MINOR.to_ne_bytes());
+        // thiszone and sigfigs, which are always zero
+        header.extend_from_slice(&[0; 8]);
+        header.extend_from_slice(&snaplen.to_ne_bytes());
+        header.extend_from_slice(&(linktype.0 as u32).to_ne_bytes());
+        inner.write_all(&header)?;
+        Ok(PcapWriter { inner })
+    }
+
+    /// Writes a packet record. Only the first `caplen` bytes of the packet are written.
+    pub fn write(&mut self, packet: &Packet) -> Result<(), Error> {
+        let header = packet.header;
+        let data = packet.data.get(..header.caplen as usize).unwrap_or(packet.data);
+        let mut record = Vec::with_capacity(16 + data.len());
+        record.extend_from_slice(&(header.ts.tv_sec as u32).to_ne_bytes());
+        record.extend_from_slice(&(header.ts.tv_usec as u32).to_ne_bytes());
+        record.extend_from_slice(&(data.len() as u32).to_ne_bytes());
+        record.extend_from_slice(&header.len.to_ne_bytes());
+        record.extend_from_slice(data);
+        self.inner.write_all(&record)?;
+        Ok(())
+    }
+
+    /// Flushes the underlying writer.
+    pub fn flush(&mut self) -> Result<(), Error> {
+        self.inner.flush()?;
+        Ok(())
+    }
+
+    /// Returns a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
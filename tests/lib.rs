@@ -1,12 +1,11 @@
-#[cfg(not(windows))]
 use std::io;
 use std::ops::Add;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use tempdir::TempDir;
 
-use pcap::{Active, Activated, Offline, Capture, Packet, PacketHeader, Linktype};
+use pcap::{Active, Activated, Offline, Capture, Packet, PacketHeader, Linktype, Error, VlanTag};
 #[cfg(not(windows))]
-use pcap::{Precision, Error};
+use pcap::Precision;
 
 #[cfg(not(windows))]
 #[allow(non_camel_case_types)]
@@ -39,6 +38,27 @@ fn capture_from_test_file(file_name: &str) -> Capture<Offline> {
     Capture::from_file(path).unwrap()
 }
 
+/// Returns a header with a zero timestamp, for parsing packets built in tests.
+fn header(caplen: u32, len: u32) -> PacketHeader {
+    PacketHeader {
+        ts: libc::timeval { tv_sec: 0, tv_usec: 0 },
+        caplen,
+        len,
+    }
+}
+
+/// Writes `packets` to a savefile named test.pcap in a new temporary directory, which is
+/// returned so that it lives as long as the caller needs the file.
+fn write_savefile(linktype: Linktype, packets: &Packets) -> (TempDir, PathBuf) {
+    let dir = TempDir::new("pcap").unwrap();
+    let tmpfile = dir.path().join("test.pcap");
+    let cap = Capture::dead(linktype).unwrap();
+    let mut save = cap.savefile(&tmpfile).unwrap();
+    packets.foreach(|p| save.write(p));
+    drop(save);
+    (dir, tmpfile)
+}
+
 #[test]
 fn unify_activated() {
 	  #![allow(dead_code)]
@@ -119,13 +139,7 @@ fn capture_dead_savefile() {
     packets.push(1460408319, 1234, 1, 1, &[1]);
     packets.push(1460408320, 4321, 1, 1, &[2]);
 
-    let dir = TempDir::new("pcap").unwrap();
-    let tmpfile = dir.path().join("test.pcap");
-
-    let cap = Capture::dead(Linktype(1)).unwrap();
-    let mut save = cap.savefile(&tmpfile).unwrap();
-    packets.foreach(|p| save.write(p));
-    drop(save);
+    let (_dir, tmpfile) = write_savefile(Linktype(1), &packets);
 
     let mut cap = Capture::from_file(&tmpfile).unwrap();
     packets.verify(&mut cap);
@@ -142,13 +156,7 @@ fn capture_dead_savefile_append() {
     packets2.push(1460408322, 5432, 1, 1, &[4]);
     let packets = &packets1 + &packets2;
 
-    let dir = TempDir::new("pcap").unwrap();
-    let tmpfile = dir.path().join("test.pcap");
-
-    let cap = Capture::dead(Linktype(1)).unwrap();
-    let mut save = cap.savefile(&tmpfile).unwrap();
-    packets1.foreach(|p| save.write(p));
-    drop(save);
+    let (_dir, tmpfile) = write_savefile(Linktype(1), &packets1);
 
     let cap = Capture::dead(Linktype(1)).unwrap();
     let mut save = cap.savefile_append(&tmpfile).unwrap();
@@ -288,3 +296,2119 @@ fn test_error() {
     // Trying to get stats from offline capture should error.
     assert!(capture.stats().err().is_some());
 }
+
+#[test]
+#[cfg(all(windows, libpcap_1_9_0))]
+fn test_buffer_size() {
+    const BUFFER_SIZE: i32 = 2 * 1024 * 1024;
+    let cap = Capture::from_device(pcap::Device::lookup().unwrap())
+        .unwrap()
+        .buffer_size(BUFFER_SIZE)
+        .open()
+        .unwrap();
+    assert_eq!(cap.buffer_size(), BUFFER_SIZE);
+}
+
+#[test]
+fn test_packet_bounds_safe_access() {
+    let header = header(10, 10);
+    let data = [0u8, 1, 2, 3, 4, 5, 6, 7, 8, 9];
+    let packet = Packet::new(&header, &data);
+
+    assert_eq!(packet.get_range(0..14), None);
+    assert_eq!(packet.get_range(2..4), Some(&data[2..4]));
+    assert_eq!(packet.split_at_checked(14), None);
+    assert_eq!(packet.split_at_checked(10), Some((&data[..], &[][..])));
+    assert_eq!(packet.split_at_checked(4), Some((&data[..4], &data[4..])));
+}
+
+#[test]
+fn test_packet_as_ref_and_iter() {
+    fn sum<B: AsRef<[u8]>>(buf: B) -> u32 {
+        buf.as_ref().iter().map(|&b| b as u32).sum()
+    }
+
+    let header = header(4, 4);
+    let data = [1u8, 2, 3, 4];
+    let packet = Packet::new(&header, &data);
+
+    assert_eq!(sum(&packet), 10);
+    assert_eq!(sum(packet.clone()), 10);
+    let collected: Vec<u8> = (&packet).into_iter().cloned().collect();
+    assert_eq!(collected, data);
+    let mut count = 0;
+    for _ in &packet {
+        count += 1;
+    }
+    assert_eq!(count, 4);
+}
+
+#[test]
+fn test_packet_direction() {
+    use pcap::Direction;
+
+    let header = header(16, 16);
+    // LINUX_SLL header: packet type, ARPHRD type, address length, address, protocol
+    let mut incoming = [0u8, 0, 0, 1, 0, 6, 1, 2, 3, 4, 5, 6, 0, 0, 0x08, 0x00];
+    assert_eq!(Packet::new(&header, &incoming).direction(Linktype::LINUX_SLL),
+               Some(Direction::In));
+    incoming[1] = 1; // broadcast
+    assert_eq!(Packet::new(&header, &incoming).direction(Linktype::LINUX_SLL),
+               Some(Direction::In));
+
+    let outgoing = [0u8, 4, 0, 1, 0, 6, 1, 2, 3, 4, 5, 6, 0, 0, 0x08, 0x00];
+    assert_eq!(Packet::new(&header, &outgoing).direction(Linktype::LINUX_SLL),
+               Some(Direction::Out));
+
+    // Link types without direction information, and truncated headers
+    assert_eq!(Packet::new(&header, &outgoing).direction(Linktype(1)), None);
+    assert_eq!(Packet::new(&header, &outgoing[..1]).direction(Linktype::LINUX_SLL), None);
+
+    let mut pktap = [0u8; 108];
+    pktap[36] = 0x02;
+    assert_eq!(Packet::new(&header, &pktap).direction(Linktype::PKTAP), Some(Direction::Out));
+    pktap[36] = 0x01;
+    assert_eq!(Packet::new(&header, &pktap).direction(Linktype::PKTAP), Some(Direction::In));
+}
+
+#[test]
+fn test_source_parse() {
+    use pcap::Source;
+    use std::path::PathBuf;
+
+    assert_eq!(Source::parse("rpcap://10.0.0.1:2002/eth0"),
+               Source::Remote { host: "10.0.0.1:2002".into(), device: "eth0".into() });
+    assert_eq!(Source::parse("rpcap://host/"),
+               Source::Remote { host: "host".into(), device: "".into() });
+    assert_eq!(Source::parse("file:///tmp/capture.pcap"),
+               Source::File(PathBuf::from("/tmp/capture.pcap")));
+    assert_eq!(Source::parse("eth0"), Source::Device("eth0".into()));
+}
+
+#[test]
+#[cfg(libpcap_1_9_0)]
+fn test_open_url_file() {
+    let mut cap = Capture::open_url("file://tests/data/packet_snaplen_65535.pcap").unwrap();
+    assert_eq!(cap.next().unwrap().len(), 98);
+}
+
+#[test]
+#[cfg(libpcap_1_9_0)]
+fn test_list_remote_files() {
+    let devices = pcap::Device::list_remote("file://tests/data/", None).unwrap();
+    assert!(devices.iter().any(|d| d.name.ends_with("packet_snaplen_20.pcap")));
+    assert!(devices.iter().any(|d| d.name.ends_with("packet_snaplen_65535.pcap")));
+}
+
+#[cfg(feature = "flow")]
+fn ipv4_frame(protocol: u8, src: [u8; 4], dst: [u8; 4], src_port: u16, dst_port: u16) -> Vec<u8> {
+    let mut frame = vec![0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb,
+                         0x08, 0x00];
+    frame.extend_from_slice(&[0x45, 0, 0, 40, 0, 0, 0x40, 0, 64, protocol, 0, 0]);
+    frame.extend_from_slice(&src);
+    frame.extend_from_slice(&dst);
+    frame.extend_from_slice(&src_port.to_be_bytes());
+    frame.extend_from_slice(&dst_port.to_be_bytes());
+    frame.extend_from_slice(&[0; 16]);
+    frame
+}
+
+#[test]
+#[cfg(feature = "flow")]
+fn test_flow_reader() {
+    use pcap::flow::{FlowKey, FlowReader};
+    use std::net::IpAddr;
+
+    let tcp = ipv4_frame(6, [10, 0, 0, 1], [10, 0, 0, 2], 40000, 80);
+    let tcp_reply = ipv4_frame(6, [10, 0, 0, 2], [10, 0, 0, 1], 80, 40000);
+    let udp = ipv4_frame(17, [10, 0, 0, 1], [10, 0, 0, 3], 5353, 53);
+    let arp = [0xffu8, 0xff, 0xff, 0xff, 0xff, 0xff, 0, 0, 0, 0, 0, 0, 0x08, 0x06, 0, 1];
+
+    let mut packets = Packets::new();
+    for (i, frame) in [&tcp[..], &arp[..], &udp[..], &tcp[..], &tcp_reply[..]].iter().enumerate() {
+        packets.push(1460408319 + i as time_t, 0, frame.len() as u32, frame.len() as u32, frame);
+    }
+
+    let (_dir, tmpfile) = write_savefile(Linktype::ETHERNET, &packets);
+
+    let tcp_key = FlowKey {
+        src: IpAddr::from([10, 0, 0, 1]),
+        dst: IpAddr::from([10, 0, 0, 2]),
+        src_port: 40000,
+        dst_port: 80,
+        protocol: 6,
+    };
+    let udp_key = FlowKey {
+        src: IpAddr::from([10, 0, 0, 1]),
+        dst: IpAddr::from([10, 0, 0, 3]),
+        src_port: 5353,
+        dst_port: 53,
+        protocol: 17,
+    };
+
+    let keys: Vec<FlowKey> = FlowReader::new(Capture::from_file(&tmpfile).unwrap())
+        .map(|item| item.unwrap().0)
+        .collect();
+    assert_eq!(keys.len(), 4);
+    assert_eq!(keys[0], tcp_key);
+    assert_eq!(keys[1], udp_key);
+
+    let flows = FlowReader::new(Capture::from_file(&tmpfile).unwrap()).into_flows().unwrap();
+    assert_eq!(flows.len(), 3);
+    assert_eq!(flows[&tcp_key].len(), 2);
+    assert_eq!(flows[&tcp_key][0].data, tcp);
+    assert_eq!(flows[&udp_key].len(), 1);
+}
+
+#[test]
+#[cfg(feature = "flow")]
+fn test_flow_key_malformed_ihl() {
+    use pcap::flow::FlowKey;
+
+    let frame = ipv4_frame(6, [10, 0, 0, 1], [10, 0, 0, 2], 40000, 80);
+    let header = header(frame.len() as u32, frame.len() as u32);
+    assert!(FlowKey::from_packet(&Packet::new(&header, &frame), Linktype::ETHERNET).is_some());
+
+    // An IHL of 0 would place the ports inside the IP header
+    let mut malformed = frame;
+    malformed[14] = 0x40;
+    assert_eq!(FlowKey::from_packet(&Packet::new(&header, &malformed), Linktype::ETHERNET), None);
+}
+
+#[test]
+#[ignore] // requires privileges to capture on the default device
+fn test_stats_since_reset() {
+    let mut cap = Capture::from_device(pcap::Device::lookup().unwrap())
+        .unwrap()
+        .timeout(100)
+        .open()
+        .unwrap();
+
+    let baseline = cap.stats_reset().unwrap();
+    let mut previous = cap.stats_since_reset().unwrap();
+    for _ in 0..5 {
+        cap.next().ok();
+        let stats = cap.stats_since_reset().unwrap();
+        assert!(stats.received >= previous.received);
+        assert!(cap.stats().unwrap().received >= baseline.received + stats.received);
+        previous = stats;
+    }
+}
+
+#[test]
+#[ignore] // requires privileges to capture on the default device
+fn test_set_immediate_mode() {
+    let mut cap = Capture::from_device(pcap::Device::lookup().unwrap())
+        .unwrap()
+        .open()
+        .unwrap();
+
+    #[cfg(any(windows, target_os = "macos", target_os = "freebsd", target_os = "openbsd",
+              target_os = "dragonfly"))]
+    {
+        assert!(cap.set_immediate_mode(true).is_ok());
+        assert!(cap.set_immediate_mode(false).is_ok());
+    }
+    #[cfg(not(any(windows, target_os = "macos", target_os = "freebsd", target_os = "openbsd",
+                  target_os = "dragonfly")))]
+    {
+        assert_eq!(cap.set_immediate_mode(true), Err(pcap::Error::Unsupported));
+    }
+}
+
+#[test]
+#[ignore] // requires privileges to capture on the loopback device
+fn test_drain() {
+    use std::net::UdpSocket;
+    use std::thread;
+    use std::time::Duration;
+
+    let device = if cfg!(target_os = "linux") { "lo" } else { "lo0" };
+    let mut cap = Capture::from_device(device)
+        .unwrap()
+        .immediate_mode(true)
+        .open()
+        .unwrap();
+    cap.filter("udp port 50124").unwrap();
+    assert_eq!(cap.drain(10).err().unwrap(), pcap::Error::NonNonBlock);
+
+    let mut cap = cap.setnonblock().unwrap();
+    assert!(cap.drain(10).unwrap().is_empty());
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    for i in 0..5u8 {
+        socket.send_to(&[i], "127.0.0.1:50124").unwrap();
+    }
+    thread::sleep(Duration::from_millis(100));
+
+    let drained = cap.drain(3).unwrap();
+    assert_eq!(drained.len(), 3);
+    assert_eq!(drained[0].data.last(), Some(&0));
+    let drained = cap.drain(10).unwrap();
+    assert_eq!(drained.len(), 2);
+    assert_eq!(drained[1].data.last(), Some(&4));
+    assert!(cap.drain(10).unwrap().is_empty());
+}
+
+#[test]
+fn test_radiotap_and_ppi_header_len() {
+    let header = header(0, 0);
+
+    // Minimal radiotap header with no fields present, followed by a frame
+    let mut data = vec![0u8, 0, 8, 0, 0, 0, 0, 0, 0x08, 0x02];
+    assert_eq!(Packet::new(&header, &data).radiotap_header_len(), Some(8));
+
+    // Radiotap header with flags and rate fields
+    data = vec![0u8, 0, 10, 0, 0x06, 0, 0, 0, 0x10, 0x02, 0x08, 0x02];
+    assert_eq!(Packet::new(&header, &data).radiotap_header_len(), Some(10));
+
+    // Header claims more bytes than were captured
+    data = vec![0u8, 0, 0x20, 0, 0, 0, 0, 0];
+    assert_eq!(Packet::new(&header, &data).radiotap_header_len(), None);
+
+    // Too short to contain the length field, or an impossibly short length
+    assert_eq!(Packet::new(&header, &[0u8, 0, 8]).radiotap_header_len(), None);
+    assert_eq!(Packet::new(&header, &[0u8, 0, 4, 0, 0, 0, 0, 0]).radiotap_header_len(), None);
+
+    // PPI header with an 802.11 DLT and no fields
+    data = vec![0u8, 0, 8, 0, 105, 0, 0, 0, 0x08, 0x02];
+    assert_eq!(Packet::new(&header, &data).ppi_header_len(), Some(8));
+    data = vec![0u8, 0, 32, 0, 105, 0, 0, 0];
+    assert_eq!(Packet::new(&header, &data).ppi_header_len(), None);
+}
+
+#[test]
+fn test_filter_compile_error() {
+    let mut capture = capture_from_test_file("packet_snaplen_65535.pcap");
+    match capture.filter("port 80 and and") {
+        Err(pcap::Error::PcapError(msg)) => assert!(msg.contains("syntax error"), "{}", msg),
+        result => panic!("unexpected result: {:?}", result),
+    }
+    // The handle is still usable with a valid filter afterwards
+    capture.filter("port 80").unwrap();
+}
+
+#[test]
+#[cfg(not(windows))]
+fn test_wait_readable_offline() {
+    use std::time::Duration;
+
+    let capture = capture_from_test_file("packet_snaplen_65535.pcap");
+    assert!(capture.selectable_fd().is_ok());
+    assert!(capture.wait_readable(Some(Duration::from_millis(100))).unwrap());
+}
+
+#[test]
+#[cfg(all(not(windows), libpcap_1_5_0))]
+#[ignore] // requires privileges to capture on the loopback device
+fn test_wait_readable_loopback() {
+    use std::net::UdpSocket;
+    use std::time::Duration;
+
+    let device = if cfg!(target_os = "linux") { "lo" } else { "lo0" };
+    let mut cap = Capture::from_device(device)
+        .unwrap()
+        .immediate_mode(true)
+        .open()
+        .unwrap();
+    cap.filter("udp port 50123").unwrap();
+
+    assert!(!cap.wait_readable(Some(Duration::from_millis(100))).unwrap());
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    socket.send_to(b"ping", "127.0.0.1:50123").unwrap();
+    assert!(cap.wait_readable(Some(Duration::from_secs(5))).unwrap());
+    assert!(cap.next().is_ok());
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+#[ignore] // requires privileges to capture on the loopback device
+fn test_effective_buffer_size() {
+    let mut cap = Capture::from_device("lo").unwrap().open().unwrap();
+    let rmem_max: usize = std::fs::read_to_string("/proc/sys/net/core/rmem_max")
+        .unwrap()
+        .trim()
+        .parse()
+        .unwrap();
+    // A size over rmem_max is clamped to it, and then doubled by the kernel
+    cap.set_kernel_rcvbuf(rmem_max + 1024 * 1024).unwrap();
+    assert_eq!(cap.effective_buffer_size().unwrap(), 2 * rmem_max);
+}
+
+#[test]
+fn test_rotating_savefile_by_size() {
+    use pcap::rotate::{RotatingSavefile, RotationPolicy};
+    use std::time::{Duration, UNIX_EPOCH};
+
+    let dir = TempDir::new("pcap").unwrap();
+    let template = dir.path().join("size-%n.pcap");
+    let cap = Capture::dead(Linktype(1)).unwrap();
+    // Each file holds the 24 byte header and up to two 66 byte records
+    let mut savefile = RotatingSavefile::new(&cap, template.to_str().unwrap(), RotationPolicy::Size(100))
+        .unwrap()
+        .with_clock(|| UNIX_EPOCH + Duration::from_secs(1460408319));
+
+    let mut packets = Packets::new();
+    for i in 0..5 {
+        packets.push(1460408319 + i, 0, 50, 50, &[i as u8; 50]);
+    }
+    packets.foreach(|p| savefile.write(p).unwrap());
+    drop(savefile);
+
+    let counts: Vec<usize> = (0..3)
+        .map(|i| {
+            let mut cap = Capture::from_file(dir.path().join(format!("size-{}.pcap", i))).unwrap();
+            std::iter::from_fn(|| cap.next().ok().map(|_| ())).count()
+        })
+        .collect();
+    assert_eq!(counts, [2, 2, 1]);
+    assert!(!dir.path().join("size-3.pcap").exists());
+}
+
+#[test]
+fn test_rotating_savefile_by_time() {
+    use pcap::rotate::{RotatingSavefile, RotationPolicy};
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, UNIX_EPOCH};
+
+    let dir = TempDir::new("pcap").unwrap();
+    let template = dir.path().join("time-%H%M%S.pcap");
+    let cap = Capture::dead(Linktype(1)).unwrap();
+    let now = Arc::new(Mutex::new(UNIX_EPOCH + Duration::from_secs(1460408319)));
+    let clock = now.clone();
+    let policy = RotationPolicy::Interval(Duration::from_secs(60));
+    let mut savefile = RotatingSavefile::new(&cap, template.to_str().unwrap(), policy)
+        .unwrap()
+        .with_clock(move || *clock.lock().unwrap());
+
+    let mut packets = Packets::new();
+    packets.push(1460408319, 0, 1, 1, &[1]);
+    packets.push(1460408349, 0, 1, 1, &[2]);
+    packets.push(1460408380, 0, 1, 1, &[3]);
+    let mut offsets = [0u64, 30, 61].iter();
+    packets.foreach(|p| {
+        *now.lock().unwrap() = UNIX_EPOCH + Duration::from_secs(1460408319 + offsets.next().unwrap());
+        savefile.write(p).unwrap();
+    });
+    drop(savefile);
+
+    let mut first = Capture::from_file(dir.path().join("time-205839.pcap")).unwrap();
+    assert_eq!(first.next().unwrap().data, [1]);
+    assert_eq!(first.next().unwrap().data, [2]);
+    assert!(first.next().is_err());
+    let mut second = Capture::from_file(dir.path().join("time-205940.pcap")).unwrap();
+    assert_eq!(second.next().unwrap().data, [3]);
+}
+
+#[test]
+fn test_linktype_known() {
+    assert!(Linktype::all_known().contains(&Linktype::ETHERNET));
+    assert!(Linktype::ETHERNET.is_known());
+    assert!(Linktype::USER15.is_known());
+    assert!(Linktype(1).is_known());
+    assert!(!Linktype(99999).is_known());
+    assert!(!Linktype(-1).is_known());
+}
+
+#[test]
+#[cfg(all(feature = "gzip", not(windows)))]
+fn read_packet_from_gzip_file() {
+    let mut capture = capture_from_test_file("packet_snaplen_65535.pcap.gz");
+    assert_eq!(capture.next().unwrap().len(), 98);
+    assert!(capture.next().is_err());
+}
+
+#[test]
+#[cfg(not(windows))]
+fn read_packet_from_bytes() {
+    let data = std::fs::read("tests/data/packet_snaplen_20.pcap").unwrap();
+    let mut capture = Capture::from_bytes(&data).unwrap();
+    assert_eq!(capture.next().unwrap().len(), 20);
+    assert!(Capture::from_bytes(&[0, 1, 2, 3]).is_err());
+}
+
+#[test]
+fn test_packet_hexdump() {
+    let header = header(20, 20);
+    let data = b"\x45\x00\x00\x54\x12\x34\x40\x00\x40\x01\x00\x00\x7f\x00\x00\x01\x7f\x00 A";
+    let packet = Packet::new(&header, data);
+    assert_eq!(packet.hexdump().to_string(),
+               "0000  45 00 00 54 12 34 40 00 40 01 00 00 7f 00 00 01  E..T.4@.@.......\n\
+                0010  7f 00 20 41                                      .. A");
+    assert_eq!(Packet::new(&header, &[]).hexdump().to_string(), "");
+}
+
+#[test]
+fn test_dead_invalid_linktype() {
+    assert_eq!(Capture::dead(Linktype(99999)).err().unwrap(), pcap::Error::InvalidLinktype);
+    assert_eq!(Capture::dead_with_snaplen(Linktype(99999), 100).err().unwrap(),
+               pcap::Error::InvalidLinktype);
+    assert!(Capture::dead(Linktype::ETHERNET).is_ok());
+    assert!(Capture::dead(Linktype::USER0).is_ok());
+    assert_eq!(Capture::dead_with_snaplen(Linktype::USER15, 100).unwrap().get_snaplen(), 100);
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn test_device_find() {
+    let device = pcap::Device::find("lo").unwrap().unwrap();
+    assert_eq!(device.name, "lo");
+    assert!(pcap::Device::list().unwrap().contains(&device));
+    assert_eq!(pcap::Device::find("no-such-device0").unwrap(), None);
+}
+
+#[test]
+fn test_savefile_with_linktype() {
+    let mut packets = Packets::new();
+    packets.push(1460408319, 1234, 1, 1, &[1]);
+
+    let dir = TempDir::new("pcap").unwrap();
+    let tmpfile = dir.path().join("test.pcap");
+
+    let cap = Capture::dead(Linktype::ETHERNET).unwrap();
+    let mut save = cap.savefile_with_linktype(&tmpfile, Linktype::USER3).unwrap();
+    packets.foreach(|p| save.write(p));
+    drop(save);
+    assert_eq!(cap.get_datalink(), Linktype::ETHERNET);
+
+    let mut cap = Capture::from_file(&tmpfile).unwrap();
+    assert_eq!(cap.get_datalink(), Linktype::USER3);
+    packets.verify(&mut cap);
+
+    // Unknown and negative link types are rejected without creating the file
+    let invalid = dir.path().join("invalid.pcap");
+    for &linktype in &[Linktype(12345), Linktype(-1)] {
+        match cap.savefile_with_linktype(&invalid, linktype) {
+            Err(pcap::Error::InvalidLinktype) => {}
+            other => panic!("{:?}", other.err()),
+        }
+    }
+    assert!(!invalid.exists());
+}
+
+#[test]
+fn test_status_to_str() {
+    // PCAP_ERROR_NO_SUCH_DEVICE
+    assert_eq!(pcap::status_to_str(-5), "No such device exists");
+    // PCAP_WARNING_PROMISC_NOTSUP
+    assert_eq!(pcap::status_to_str(2), "That device doesn't support promiscuous mode");
+}
+
+#[test]
+fn test_next_parsed() {
+    let mut cap = capture_from_test_file("packet_snaplen_65535.pcap");
+    assert_eq!(cap.get_datalink(), Linktype::ETHERNET);
+    let (packet, payload) = cap.next_parsed().unwrap();
+    let payload = payload.unwrap();
+    assert_eq!(packet.len() - payload.len(), 14);
+    assert_eq!(payload, &packet[14..]);
+    assert_eq!(payload[0], 0x45);
+}
+
+#[test]
+fn test_set_datalink_savefile_header() {
+    let dir = TempDir::new("pcap").unwrap();
+    let tmpfile = dir.path().join("test.pcap");
+
+    let mut cap = capture_from_test_file("packet_snaplen_65535.pcap");
+    assert!(cap.set_datalink(Linktype::RAW).is_err());
+    cap.set_datalink(Linktype::ETHERNET).unwrap();
+    assert_eq!(cap.get_datalink(), Linktype::ETHERNET);
+
+    let mut save = cap.savefile(&tmpfile).unwrap();
+    let packet = cap.next().unwrap();
+    let data = packet.data.to_vec();
+    save.write(&packet);
+    drop(save);
+
+    let mut cap = Capture::from_file(&tmpfile).unwrap();
+    assert_eq!(cap.get_datalink(), Linktype::ETHERNET);
+    assert_eq!(cap.next().unwrap().data, &data[..]);
+}
+
+#[test]
+#[ignore] // requires privileges to capture on the default device
+fn test_sendpacket_too_large() {
+    let mut cap = pcap::Device::lookup().unwrap().open().unwrap();
+    let buf = vec![0u8; 70000];
+    assert_eq!(cap.sendpacket(&buf[..]).err().unwrap(),
+               Error::PacketTooLarge { len: 70000, max: 65535 });
+
+    cap.set_max_packet_len(9000);
+    assert_eq!(cap.sendpacket(&buf[..9001]).err().unwrap(),
+               Error::PacketTooLarge { len: 9001, max: 9000 });
+}
+
+#[test]
+fn test_rewind() {
+    let mut cap = capture_from_test_file("packet_snaplen_65535.pcap");
+    let first = cap.next_owned().unwrap();
+    while cap.next().is_ok() {}
+    cap.rewind().unwrap();
+    let again = cap.next_owned().unwrap();
+    assert_eq!(again.data, first.data);
+    assert_eq!(again.header, first.header);
+}
+
+#[test]
+fn test_rewind_reopen_keeps_filter() {
+    // pcapng savefiles are rewound by reopening them
+    let mut cap = capture_from_test_file("packet_snaplen_65535.pcapng");
+    cap.filter("tcp").unwrap();
+    assert_eq!(cap.next().err(), Some(pcap::Error::NoMorePackets));
+    cap.rewind().unwrap();
+    assert_eq!(cap.get_filter(), Some("tcp"));
+    assert_eq!(cap.next().err(), Some(pcap::Error::NoMorePackets));
+
+    cap.filter("icmp").unwrap();
+    cap.rewind().unwrap();
+    assert!(cap.next().is_ok());
+}
+
+#[test]
+#[cfg(feature = "capture-stream")]
+fn test_closure_codec() {
+    use pcap::stream::PacketCodec;
+
+    let header = header(3, 10);
+    let mut codec = |p: Packet| (p.len(), p.header.len);
+    assert_eq!(codec.decode(Packet::new(&header, &[1, 2, 3])).unwrap(), (3, 10));
+}
+
+#[test]
+#[cfg(feature = "capture-stream")]
+#[ignore] // requires privileges to capture on the loopback device
+fn test_closure_codec_stream() {
+    use futures::StreamExt;
+    use std::net::UdpSocket;
+
+    let mut rt = tokio::runtime::Builder::new()
+        .enable_io()
+        .basic_scheduler()
+        .build()
+        .unwrap();
+    let mut stream = rt.enter(|| {
+        let mut cap = Capture::from_device("lo")
+            .unwrap()
+            .immediate_mode(true)
+            .open()
+            .unwrap()
+            .setnonblock()
+            .unwrap();
+        cap.filter("udp port 50125").unwrap();
+        cap.stream(|p: Packet| p.header.len).unwrap()
+    });
+
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    socket.send_to(&[0; 5], "127.0.0.1:50125").unwrap();
+
+    // Ethernet, IPv4 and UDP headers followed by the payload
+    let len = rt.block_on(stream.next()).unwrap().unwrap();
+    assert_eq!(len, 14 + 20 + 8 + 5);
+}
+
+#[test]
+fn test_set_filter_raw() {
+    use pcap::BpfInstruction;
+
+    // BPF_RET | BPF_K
+    const RET_K: u16 = 0x06;
+
+    let mut cap = capture_from_test_file("packet_snaplen_65535.pcap");
+    cap.set_filter_raw(&[BpfInstruction { code: RET_K, jt: 0, jf: 0, k: 0xffff }]).unwrap();
+    assert!(cap.next().is_ok());
+
+    let mut cap = capture_from_test_file("packet_snaplen_65535.pcap");
+    cap.set_filter_raw(&[BpfInstruction { code: RET_K, jt: 0, jf: 0, k: 0 }]).unwrap();
+    assert_eq!(cap.next().err().unwrap(), Error::NoMorePackets);
+}
+
+#[test]
+fn test_last_error() {
+    let mut cap = capture_from_test_file("packet_snaplen_65535.pcap");
+    let err = cap.filter("not a (valid filter").unwrap_err();
+    let msg = cap.last_error().unwrap();
+    assert!(msg.contains("syntax error"), "{}", msg);
+    assert_eq!(err, Error::PcapError(msg));
+}
+
+#[test]
+fn test_tuples() {
+    let mut packets = Packets::new();
+    packets.push(1460408319, 1234, 1, 1, &[1]);
+    packets.push(1460408320, 4321, 2, 3, &[2, 3]);
+
+    let (_dir, tmpfile) = write_savefile(Linktype(1), &packets);
+
+    let mut cap = Capture::from_file(&tmpfile).unwrap();
+    let tuples = cap.tuples().collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(tuples.len(), 2);
+    for (i, (header, data)) in tuples.iter().enumerate() {
+        assert_eq!(*header, packets.headers[i]);
+        assert_eq!(*data, packets.data[i]);
+    }
+    assert_eq!(cap.tuples().count(), 0);
+}
+
+#[test]
+#[ignore] // requires privileges to capture on the default device
+fn test_set_timeout_active() {
+    let mut cap = Capture::from_device(pcap::Device::lookup().unwrap())
+        .unwrap()
+        .timeout(100)
+        .open()
+        .unwrap();
+
+    // No released libpcap allows the timeout to be changed after activation, and the rejected
+    // call must leave the handle usable.
+    assert_eq!(cap.set_timeout(10), Err(Error::Unsupported));
+    match cap.next() {
+        Ok(_) | Err(pcap::Error::TimeoutExpired) => {}
+        Err(e) => panic!("unexpected error: {}", e),
+    }
+}
+
+#[test]
+fn test_device_flags_connection_status() {
+    use pcap::{ConnectionStatus, DeviceFlags};
+
+    let status = |bits| DeviceFlags::from_bits_truncate(bits).connection_status();
+    assert_eq!(status(0x00), ConnectionStatus::Unknown);
+    assert_eq!(status(0x10), ConnectionStatus::Connected);
+    assert_eq!(status(0x20), ConnectionStatus::Disconnected);
+    assert_eq!(status(0x30), ConnectionStatus::NotApplicable);
+
+    let flags = DeviceFlags::from_bits_truncate(0x37);
+    assert!(flags.is_loopback() && flags.is_up() && flags.is_running() && !flags.is_wireless());
+    assert_eq!(flags.connection_status(), ConnectionStatus::NotApplicable);
+    assert_eq!(DeviceFlags::from_bits_truncate(0x1c).connection_status(),
+               ConnectionStatus::Connected);
+}
+
+#[test]
+fn test_from_ptr() {
+    use pcap::{Dead, pcap_t};
+
+    extern "C" {
+        fn pcap_open_dead(linktype: libc::c_int, snaplen: libc::c_int) -> *mut pcap_t;
+    }
+
+    let handle = unsafe { pcap_open_dead(Linktype::USER0.0, 1234) };
+    assert!(!handle.is_null());
+    let cap = unsafe { Capture::<Dead>::from_ptr(handle) };
+    assert_eq!(cap.get_datalink(), Linktype::USER0);
+    assert_eq!(cap.get_snaplen(), 1234);
+}
+
+#[test]
+fn test_into_raw_round_trip() {
+    use pcap::Dead;
+
+    let cap = Capture::dead_with_snaplen(Linktype::USER1, 4321).unwrap();
+    let handle = cap.into_raw();
+    assert!(!handle.is_null());
+    let cap = unsafe { Capture::<Dead>::from_ptr(handle) };
+    assert_eq!(cap.get_datalink(), Linktype::USER1);
+    assert_eq!(cap.get_snaplen(), 4321);
+
+    let handle = capture_from_test_file("packet_snaplen_65535.pcap").into_raw();
+    let mut cap = unsafe { Capture::<Offline>::from_ptr(handle) };
+    assert_eq!(cap.next().unwrap().len(), 98);
+}
+
+#[test]
+#[cfg(windows)]
+#[ignore] // requires privileges to capture on the default device
+fn test_stats_ex() {
+    let mut cap = Capture::from_device(pcap::Device::lookup().unwrap())
+        .unwrap()
+        .timeout(100)
+        .open()
+        .unwrap();
+
+    let _ = cap.next();
+    let stats = cap.stats().unwrap();
+    let stats_ex = cap.stats_ex().unwrap();
+    assert!(stats_ex.received >= stats.received);
+    assert!(stats_ex.dropped >= stats.dropped);
+}
+
+#[test]
+#[cfg(not(windows))]
+#[ignore] // requires privileges to capture on the loopback device
+fn test_next_interrupted_by_signal() {
+    use std::net::UdpSocket;
+    use std::thread;
+    use std::time::Duration;
+
+    extern "C" fn ignore_signal(_: libc::c_int) {}
+
+    // Install a handler without SA_RESTART, so that the signal interrupts blocking reads
+    unsafe {
+        let mut action: libc::sigaction = std::mem::zeroed();
+        action.sa_sigaction = ignore_signal as extern "C" fn(libc::c_int) as usize;
+        assert_eq!(libc::sigaction(libc::SIGUSR1, &action, std::ptr::null_mut()), 0);
+    }
+
+    let device = if cfg!(target_os = "linux") { "lo" } else { "lo0" };
+    let mut cap = Capture::from_device(device)
+        .unwrap()
+        .immediate_mode(true)
+        .timeout(1000)
+        .open()
+        .unwrap();
+    cap.filter("udp port 4977").unwrap();
+
+    let reader = unsafe { libc::pthread_self() };
+    let sender = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(100));
+        unsafe { libc::pthread_kill(reader, libc::SIGUSR1) };
+        thread::sleep(Duration::from_millis(100));
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket.send_to(b"after signal", "127.0.0.1:4977").unwrap();
+    });
+
+    // The signal may or may not be reported depending on the platform, but either way the
+    // capture loop must go on to receive the packet sent after it.
+    let received = loop {
+        match cap.next() {
+            Ok(packet) => break packet.len(),
+            Err(Error::Interrupted) | Err(Error::TimeoutExpired) => continue,
+            Err(e) => panic!("unexpected error: {}", e),
+        }
+    };
+    assert!(received > 0);
+    sender.join().unwrap();
+}
+
+#[test]
+fn test_packet_timestamp_nanos() {
+    // 2013-05-30 22:45:17.283187 UTC
+    const NANOS: i128 = 1_369_953_917_283_187_000;
+
+    let mut cap = capture_from_test_file("packet_snaplen_65535.pcap");
+    let packet = cap.next_owned().unwrap();
+    assert_eq!(cap.packet_timestamp_nanos(&packet.as_packet()), NANOS);
+
+    #[cfg(libpcap_1_5_0)]
+    {
+        let path = Path::new("tests/data/packet_snaplen_65535.pcap");
+        let mut cap = Capture::from_file_with_precision(path, pcap::Precision::Nano).unwrap();
+        let packet = cap.next_owned().unwrap();
+        assert_eq!(packet.header.ts.tv_usec, 283_187_000);
+        assert_eq!(cap.packet_timestamp_nanos(&packet.as_packet()), NANOS);
+    }
+
+    let header = PacketHeader {
+        ts: libc::timeval { tv_sec: -2, tv_usec: 500_000 },
+        caplen: 0,
+        len: 0,
+    };
+    assert_eq!(cap.packet_timestamp_nanos(&Packet::new(&header, &[])), -1_500_000_000);
+}
+
+#[test]
+fn test_list_datalinks_described() {
+    let cap = capture_from_test_file("packet_snaplen_65535.pcap");
+    let datalinks = cap.list_datalinks_described().unwrap();
+    assert_eq!(datalinks,
+               vec![(Linktype::ETHERNET, "EN10MB".to_string(), "Ethernet".to_string())]);
+}
+
+#[test]
+#[ignore] // requires privileges to capture on the loopback device
+fn test_send_all() {
+    let device = if cfg!(target_os = "linux") { "lo" } else { "lo0" };
+    let mut cap = Capture::from_device(device).unwrap().open().unwrap();
+
+    // Loopback frames with a zeroed Ethernet header
+    let frames = [vec![0u8; 60], vec![0u8; 64], vec![0u8; 128]];
+    assert_eq!(cap.send_all(frames.iter().map(Vec::as_slice)).unwrap(), 3);
+
+    let frames = vec![vec![0u8; 60], vec![0u8; 70000], vec![0u8; 60]];
+    assert_eq!(cap.send_all(frames).err().unwrap(),
+               Error::PartialSend {
+                   sent: 1,
+                   error: Box::new(Error::PacketTooLarge { len: 70000, max: 65535 }),
+               });
+}
+
+#[test]
+fn test_direction_default() {
+    use pcap::Direction;
+
+    assert_eq!(Direction::default(), Direction::InOut);
+    let cap = capture_from_test_file("packet_snaplen_65535.pcap");
+    assert_eq!(cap.get_direction(), Direction::InOut);
+}
+
+#[test]
+#[ignore] // requires privileges to capture on the default device
+fn test_get_direction() {
+    use pcap::Direction;
+
+    let cap = pcap::Device::lookup().unwrap().open().unwrap();
+    assert_eq!(cap.get_direction(), Direction::default());
+    cap.direction(Direction::In).unwrap();
+    assert_eq!(cap.get_direction(), Direction::In);
+}
+
+#[test]
+fn test_file_format() {
+    use pcap::FileFormat;
+
+    let format = |name| Capture::file_format(Path::new("tests/data/").join(name));
+    assert_eq!(format("packet_snaplen_65535.pcap").unwrap(), FileFormat::Pcap { nanos: false });
+    assert_eq!(format("packet_snaplen_65535.pcapng").unwrap(), FileFormat::PcapNg);
+    assert!(format("packet_snaplen_65535.pcap.gz").is_err());
+    assert!(format("no_such_file.pcap").is_err());
+}
+
+#[test]
+fn test_savefile_write_owned() {
+    let mut cap = capture_from_test_file("packet_snaplen_65535.pcap");
+    let mut packets = vec![];
+    while let Ok(packet) = cap.next_owned() {
+        packets.push(packet);
+    }
+    assert!(!packets.is_empty());
+
+    let dir = TempDir::new("pcap").unwrap();
+    let tmpfile = dir.path().join("test.pcap");
+    let mut save = cap.savefile(&tmpfile).unwrap();
+    for packet in &packets {
+        save.write_owned(packet);
+    }
+    drop(save);
+
+    let mut cap = Capture::from_file(&tmpfile).unwrap();
+    for packet in &packets {
+        assert_eq!(cap.next().unwrap(), packet.as_packet());
+    }
+    assert!(cap.next().is_err());
+}
+
+#[test]
+#[ignore] // requires privileges to capture on the default device
+fn test_open_with_warning_snaplen_clamped() {
+    let (cap, warnings) = Capture::from_device(pcap::Device::lookup().unwrap())
+        .unwrap()
+        .snaplen(1_000_000)
+        .open_with_warning()
+        .unwrap();
+    assert!(cap.get_snaplen() < 1_000_000);
+    assert!(warnings.iter().any(|w| w.message.contains("clamped")), "{:?}", warnings);
+}
+
+#[test]
+fn test_next_with_header() {
+    let mut cap = capture_from_test_file("packet_snaplen_65535.pcap");
+    let mut header = header(0, 0);
+    let len = cap.next_with_header(&mut header).unwrap().len();
+    assert_eq!(header.caplen as usize, len);
+    assert_eq!(header.len, 98);
+    assert_eq!(header.ts.tv_sec, 1369953917);
+}
+
+#[test]
+fn test_count_packets() {
+    let mut packets = Packets::new();
+    packets.push(1460408319, 1234, 1, 1, &[1]);
+    packets.push(1460408320, 4321, 1, 1, &[2]);
+    packets.push(1460408321, 5678, 2, 2, &[3, 4]);
+
+    let (_dir, tmpfile) = write_savefile(Linktype(1), &packets);
+
+    let mut cap = Capture::from_file(&tmpfile).unwrap();
+    assert_eq!(cap.count_packets().unwrap(), 3);
+    packets.verify(&mut cap);
+}
+
+#[test]
+#[cfg(all(target_os = "linux", libpcap_1_2_1))]
+fn test_try_builders() {
+    use pcap::TimestampType;
+
+    // Inactive handles can be created and configured without privileges
+    let cap = Capture::from_device("lo")
+        .unwrap()
+        .try_snaplen(128)
+        .map_err(|(_, e)| e)
+        .unwrap()
+        .try_promisc(false)
+        .map_err(|(_, e)| e)
+        .unwrap();
+
+    // The loopback device has no hardware time stamps
+    let (cap, err) = cap.try_tstamp_type(TimestampType::Adapter).err().unwrap();
+    assert_eq!(err, Error::PcapError(pcap::status_to_str(3)));
+
+    // The capture is still usable after a rejected setting
+    assert!(cap.try_timeout(100).is_ok());
+}
+
+#[test]
+fn test_lib_version() {
+    assert!(pcap::lib_version().contains("libpcap"));
+    let (major, _, _) = pcap::parsed_version().unwrap();
+    assert!(major >= 1);
+}
+
+#[test]
+fn test_set_datalink_or_default() {
+    let mut cap = capture_from_test_file("packet_snaplen_65535.pcap");
+    let preferred = [Linktype::RAW, Linktype::ETHERNET];
+    assert_eq!(cap.set_datalink_or_default(&preferred).unwrap(), Linktype::ETHERNET);
+    assert_eq!(cap.set_datalink_or_default(&[Linktype::RAW]).unwrap(), Linktype::ETHERNET);
+    assert_eq!(cap.set_datalink_or_default(&[]).unwrap(), Linktype::ETHERNET);
+    assert_eq!(cap.get_datalink(), Linktype::ETHERNET);
+}
+
+#[test]
+fn test_packet_ethertype() {
+    let header = header(0, 0);
+    let frame = |types: &[u8]| {
+        let mut data = vec![0u8; 12];
+        data.extend_from_slice(types);
+        data.extend_from_slice(&[0x45, 0, 0, 20]);
+        data
+    };
+
+    let ethertype = |data: &[u8]| Packet::new(&header, data).ethertype(Linktype::ETHERNET);
+    assert_eq!(ethertype(&frame(&[0x08, 0x00])), Some(0x0800));
+    assert_eq!(ethertype(&frame(&[0x86, 0xdd])), Some(0x86dd));
+    assert_eq!(ethertype(&frame(&[0x08, 0x06])), Some(0x0806));
+    // 802.1Q tag with VLAN ID 100
+    assert_eq!(ethertype(&frame(&[0x81, 0x00, 0x00, 0x64, 0x08, 0x00])), Some(0x0800));
+    // QinQ
+    assert_eq!(ethertype(&frame(&[0x88, 0xa8, 0x00, 0x01, 0x81, 0x00, 0x00, 0x64, 0x86, 0xdd])),
+               Some(0x86dd));
+    // 802.3 length field
+    assert_eq!(ethertype(&frame(&[0x00, 0x2e])), None);
+    // Truncated frames
+    assert_eq!(ethertype(&[0u8; 13]), None);
+    assert_eq!(ethertype(&frame(&[0x81, 0x00])[..16]), None);
+    // Other link types, even if the bytes would parse as an Ethernet frame
+    let data = frame(&[0x08, 0x00]);
+    assert_eq!(Packet::new(&header, &data).ethertype(Linktype::LINUX_SLL), None);
+    assert_eq!(Packet::new(&header, &data).ethertype(Linktype::RAW), None);
+
+    let mut cap = capture_from_test_file("packet_snaplen_65535.pcap");
+    let linktype = cap.get_datalink();
+    assert_eq!(cap.next().unwrap().ethertype(linktype), Some(0x0800));
+}
+
+#[test]
+fn test_read_all() {
+    let mut packets = Packets::new();
+    packets.push(1460408319, 1234, 1, 1, &[1]);
+    packets.push(1460408320, 4321, 2, 2, &[2, 3]);
+    packets.push(1460408321, 5678, 3, 3, &[4, 5, 6]);
+
+    let (_dir, tmpfile) = write_savefile(Linktype(1), &packets);
+
+    let mut cap = Capture::from_file(&tmpfile).unwrap();
+    let all = cap.read_all(None, None).unwrap();
+    assert_eq!(all.iter().map(|p| p.data.clone()).collect::<Vec<_>>(), packets.data);
+
+    let mut cap = Capture::from_file(&tmpfile).unwrap();
+    assert_eq!(cap.read_all(Some(3), Some(6)).unwrap().len(), 3);
+
+    let mut cap = Capture::from_file(&tmpfile).unwrap();
+    assert_eq!(cap.read_all(None, Some(5)).unwrap_err(), Error::ReadLimitExceeded);
+
+    let mut cap = Capture::from_file(&tmpfile).unwrap();
+    assert_eq!(cap.read_all(Some(2), None).unwrap_err(), Error::ReadLimitExceeded);
+}
+
+#[test]
+fn test_error_predicates() {
+    assert!(Error::TimeoutExpired.is_timeout());
+    assert!(!Error::TimeoutExpired.is_eof());
+    assert!(Error::NoMorePackets.is_eof());
+    assert!(!Error::NoMorePackets.is_timeout());
+    assert!(Error::IoError(io::ErrorKind::WouldBlock).is_would_block());
+    assert!(!Error::IoError(io::ErrorKind::NotFound).is_would_block());
+    assert!(!Error::InvalidLinktype.is_timeout());
+    assert!(!Error::InvalidLinktype.is_eof());
+    assert!(!Error::InvalidLinktype.is_would_block());
+}
+
+#[test]
+fn test_offline_try_clone() {
+    let mut packets = Packets::new();
+    packets.push(1460408319, 1234, 1, 1, &[1]);
+    packets.push(1460408320, 4321, 1, 1, &[2]);
+
+    let (_dir, tmpfile) = write_savefile(Linktype(1), &packets);
+
+    let mut cap = Capture::from_file(&tmpfile).unwrap();
+    assert_eq!(cap.next().unwrap().data, &[1]);
+    let mut clone = cap.try_clone().unwrap();
+    assert_eq!(cap.next().unwrap().data, &[2]);
+    assert!(cap.next().is_err());
+    packets.verify(&mut clone);
+
+    #[cfg(not(windows))]
+    {
+        let data = std::fs::read(&tmpfile).unwrap();
+        let cap = Capture::from_bytes(&data).unwrap();
+        assert_eq!(cap.try_clone().err().unwrap(), Error::Unsupported);
+    }
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+#[ignore] // requires privileges to capture on the loopback device
+fn test_set_kernel_rcvbuf() {
+    let mut cap = Capture::from_device("lo").unwrap().open().unwrap();
+    cap.set_kernel_rcvbuf(65536).unwrap();
+    // Linux doubles the requested size to allow for bookkeeping overhead
+    assert_eq!(cap.effective_buffer_size().unwrap(), 2 * 65536);
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+#[ignore] // requires privileges to capture on the loopback device
+fn test_stats_handle() {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+
+    let mut cap = Capture::from_device("lo").unwrap().timeout(10).open().unwrap();
+    let handle = cap.stats_handle();
+    let done = Arc::new(AtomicBool::new(false));
+    let reader = {
+        let done = done.clone();
+        thread::spawn(move || {
+            let mut reads = 0;
+            while !done.load(Ordering::SeqCst) {
+                handle.stats().unwrap();
+                reads += 1;
+            }
+            (handle, reads)
+        })
+    };
+    for _ in 0..10 {
+        match cap.next() {
+            Ok(_) | Err(Error::TimeoutExpired) => {}
+            Err(e) => panic!("{:?}", e),
+        }
+    }
+    cap.stats_shared().unwrap();
+    done.store(true, Ordering::SeqCst);
+    let (handle, reads) = reader.join().unwrap();
+    assert!(reads > 0);
+
+    drop(cap);
+    assert!(!handle.is_alive());
+    assert_eq!(handle.stats(), Err(Error::CaptureClosed));
+}
+
+#[test]
+fn test_packet_vlan_tags() {
+    let header = header(0, 0);
+    let frame = |types: &[u8]| {
+        let mut data = vec![0u8; 12];
+        data.extend_from_slice(types);
+        data.extend_from_slice(&[0x45, 0, 0, 20]);
+        data
+    };
+
+    // Untagged
+    let data = frame(&[0x08, 0x00]);
+    let packet = Packet::new(&header, &data);
+    assert_eq!(packet.vlan_tags(), vec![]);
+    assert_eq!(packet.strip_vlan(), Some(&[0x45, 0, 0, 20][..]));
+
+    // 802.1Q tag with priority 5 and VLAN ID 100
+    let data = frame(&[0x81, 0x00, 0xa0, 0x64, 0x08, 0x00]);
+    let packet = Packet::new(&header, &data);
+    assert_eq!(packet.vlan_tags(),
+               vec![VlanTag { tpid: 0x8100, priority: 5, drop_eligible: false, id: 100 }]);
+    assert_eq!(packet.strip_vlan(), Some(&[0x45, 0, 0, 20][..]));
+
+    // QinQ, with the drop eligible indicator set on the service tag
+    let data = frame(&[0x88, 0xa8, 0x10, 0x01, 0x81, 0x00, 0x0f, 0xff, 0x86, 0xdd]);
+    let packet = Packet::new(&header, &data);
+    assert_eq!(packet.vlan_tags(), vec![
+        VlanTag { tpid: 0x88a8, priority: 0, drop_eligible: true, id: 1 },
+        VlanTag { tpid: 0x8100, priority: 0, drop_eligible: false, id: 4095 },
+    ]);
+    assert_eq!(packet.strip_vlan(), Some(&[0x45, 0, 0, 20][..]));
+
+    // Truncated tag
+    let data = frame(&[0x81, 0x00]);
+    let packet = Packet::new(&header, &data[..15]);
+    assert_eq!(packet.vlan_tags(), vec![]);
+    assert_eq!(packet.strip_vlan(), None);
+}
+
+#[test]
+#[cfg(target_os = "macos")]
+#[ignore] // requires privileges and a Wi-Fi interface
+fn test_rfmon_activation_error() {
+    use pcap::ActivationError;
+
+    match Capture::from_device("en0").unwrap().rfmon(true).open() {
+        Ok(_) => {}
+        Err(Error::Activation { kind: ActivationError::RfmonNotSupported, .. }) => {}
+        Err(Error::Activation { kind: ActivationError::InterfaceNotUp, .. }) => {}
+        Err(e) => panic!("unexpected error: {:?}", e),
+    }
+}
+
+#[test]
+#[cfg(libpcap_1_7_2)]
+fn test_savefile_append_mismatch() {
+    let (_dir, tmpfile) = write_savefile(Linktype::ETHERNET, &Packets::new());
+
+    let cap = Capture::dead(Linktype::RAW).unwrap();
+    match cap.savefile_append(&tmpfile) {
+        Err(Error::AppendMismatch { field, existing, new }) => {
+            assert_eq!(field, "linktype");
+            assert_eq!(existing, format!("{:?}", Linktype::ETHERNET));
+            assert_eq!(new, format!("{:?}", Linktype::RAW));
+        }
+        Err(e) => panic!("unexpected error: {:?}", e),
+        Ok(_) => panic!("appended to a savefile with a different linktype"),
+    }
+
+    let cap = Capture::dead_with_snaplen(Linktype::ETHERNET, 128).unwrap();
+    match cap.savefile_append(&tmpfile) {
+        Err(Error::AppendMismatch { field, .. }) => assert_eq!(field, "snaplen"),
+        Err(e) => panic!("unexpected error: {:?}", e),
+        Ok(_) => panic!("appended to a savefile with a different snaplen"),
+    }
+
+    let cap = Capture::dead(Linktype::ETHERNET).unwrap();
+    assert!(cap.savefile_append(&tmpfile).is_ok());
+}
+
+#[test]
+#[cfg(feature = "capture-stream")]
+#[ignore] // requires privileges to capture on the loopback device
+fn test_stream_report_drops() {
+    use futures::StreamExt;
+    use pcap::stream::StreamItem;
+    use std::net::UdpSocket;
+    use std::thread;
+    use std::time::Duration;
+
+    let mut rt = tokio::runtime::Builder::new()
+        .enable_io()
+        .basic_scheduler()
+        .build()
+        .unwrap();
+    let stream = rt.enter(|| {
+        Capture::from_device("lo")
+            .unwrap()
+            .buffer_size(1024)
+            .immediate_mode(true)
+            .open()
+            .unwrap()
+            .setnonblock()
+            .unwrap()
+            .stream(|p: Packet| p.len())
+            .unwrap()
+            .report_drops(1)
+            .unwrap()
+    });
+
+    // Flood the loopback device faster than the consumer below reads packets
+    thread::spawn(|| {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        for _ in 0..100_000 {
+            let _ = socket.send_to(&[0; 512], "127.0.0.1:9");
+        }
+    });
+
+    let dropped = rt.block_on(stream
+        .filter_map(|item| futures::future::ready(match item.unwrap() {
+            StreamItem::Packet(_) => {
+                thread::sleep(Duration::from_millis(1));
+                None
+            }
+            StreamItem::Dropped(count) => Some(count),
+        }))
+        .next());
+    assert!(dropped.unwrap() > 0);
+}
+
+#[test]
+#[cfg(feature = "capture-stream")]
+#[ignore] // requires privileges to capture on the loopback device
+fn test_stream_buffered() {
+    use futures::StreamExt;
+    use pcap::stream::StreamItem;
+    use std::net::UdpSocket;
+    use std::thread;
+    use std::time::Duration;
+
+    let mut rt = tokio::runtime::Builder::new()
+        .enable_io()
+        .basic_scheduler()
+        .build()
+        .unwrap();
+    let stream = rt.enter(|| {
+        Capture::from_device("lo")
+            .unwrap()
+            .buffer_size(1024)
+            .immediate_mode(true)
+            .open()
+            .unwrap()
+            .setnonblock()
+            .unwrap()
+            .stream(|p: Packet| p.len())
+            .unwrap()
+            .buffered(16, 1)
+            .unwrap()
+    });
+
+    // Flood the loopback device while the consumer below lags behind the full channel
+    thread::spawn(|| {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        for _ in 0..100_000 {
+            let _ = socket.send_to(&[0; 512], "127.0.0.1:9");
+        }
+    });
+
+    let items = rt.block_on(stream
+        .map(|item| {
+            thread::sleep(Duration::from_millis(1));
+            item.unwrap()
+        })
+        .skip_while(|item| futures::future::ready(match *item {
+            StreamItem::Packet(_) => true,
+            StreamItem::Dropped(_) => false,
+        }))
+        .take(2)
+        .collect::<Vec<_>>());
+    // The drops are reported between packets, and the stream carries on after them
+    match items[..] {
+        [StreamItem::Dropped(count), StreamItem::Packet(_)] => assert!(count > 0),
+        ref items => panic!("unexpected items: {:?}", items),
+    }
+}
+
+#[test]
+fn test_filter_from_file() {
+    let dir = TempDir::new("pcap").unwrap();
+    let path = dir.path().join("filter");
+
+    std::fs::write(&path, "# Only ICMP\nicmp and # echo requests\n  icmp[icmptype] = 8\n\n").unwrap();
+    let mut cap = capture_from_test_file("packet_snaplen_65535.pcap");
+    cap.filter_from_file(&path).unwrap();
+    assert!(cap.next().is_ok());
+
+    std::fs::write(&path, "# TCP only\ntcp\n").unwrap();
+    let mut cap = capture_from_test_file("packet_snaplen_65535.pcap");
+    cap.filter_from_file(&path).unwrap();
+    assert_eq!(cap.next().err().unwrap(), Error::NoMorePackets);
+
+    std::fs::write(&path, "not a (valid filter\n").unwrap();
+    match cap.filter_from_file(&path) {
+        Err(Error::PcapError(_)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+    assert_eq!(cap.filter_from_file(dir.path().join("missing")),
+               Err(Error::IoError(io::ErrorKind::NotFound)));
+}
+
+#[test]
+fn test_packet_pktap_metadata() {
+    use pcap::{Direction, PktapMetadata};
+
+    let header = header(0, 0);
+    // A PKTAP header as written by macOS, followed by the start of an Ethernet frame
+    let mut data = vec![0u8; 156 + 14];
+    data[0..4].copy_from_slice(&156u32.to_le_bytes());
+    data[4..8].copy_from_slice(&1u32.to_le_bytes());
+    data[8..12].copy_from_slice(&1u32.to_le_bytes());
+    data[12..15].copy_from_slice(b"en0");
+    data[36..40].copy_from_slice(&2u32.to_le_bytes());
+    data[52..56].copy_from_slice(&1234i32.to_le_bytes());
+    data[56..62].copy_from_slice(b"Safari");
+    data[84..88].copy_from_slice(&(-1i32).to_le_bytes());
+    // The process name fills its field, without a terminating NUL
+    data[88..105].copy_from_slice(b"com.apple.WebKit.");
+
+    let packet = Packet::new(&header, &data);
+    assert_eq!(packet.pktap_metadata(), Some(PktapMetadata {
+        header_len: 156,
+        linktype: Linktype::ETHERNET,
+        interface: "en0".to_string(),
+        pid: 1234,
+        process: "Safari".to_string(),
+        effective_pid: -1,
+        effective_process: "com.apple.WebKit.".to_string(),
+    }));
+    assert_eq!(packet.direction(Linktype::PKTAP), Some(Direction::Out));
+
+    // Truncated headers
+    assert_eq!(Packet::new(&header, &data[..100]).pktap_metadata(), None);
+    data[0..4].copy_from_slice(&64u32.to_le_bytes());
+    assert_eq!(Packet::new(&header, &data).pktap_metadata(), None);
+}
+
+#[test]
+#[cfg(target_os = "macos")]
+fn test_device_pktap() {
+    use pcap::Device;
+
+    assert_eq!(Device::pktap(&[]).name, "pktap");
+    assert_eq!(Device::pktap(&["en0", "en1"]).name, "pktap,en0,en1");
+}
+
+#[test]
+fn test_pcapng_linktype_per_packet() {
+    use pcap::pcapng::OfflineNg;
+
+    fn block(block_type: u32, body: &[u8]) -> Vec<u8> {
+        let len = (12 + body.len()) as u32;
+        let mut block = block_type.to_le_bytes().to_vec();
+        block.extend_from_slice(&len.to_le_bytes());
+        block.extend_from_slice(body);
+        block.extend_from_slice(&len.to_le_bytes());
+        block
+    }
+    fn epb(interface: u32, ts: u64, data: &[u8]) -> Vec<u8> {
+        let mut body = interface.to_le_bytes().to_vec();
+        body.extend_from_slice(&((ts >> 32) as u32).to_le_bytes());
+        body.extend_from_slice(&(ts as u32).to_le_bytes());
+        body.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        body.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        body.extend_from_slice(data);
+        body.resize((body.len() + 3) & !3, 0);
+        block(6, &body)
+    }
+
+    // Section header: byte order magic, version 1.0, unknown section length
+    let mut file = block(0x0a0d_0d0a, &[0x4d, 0x3c, 0x2b, 0x1a, 1, 0, 0, 0, 0xff, 0xff, 0xff,
+                                        0xff, 0xff, 0xff, 0xff, 0xff]);
+    // An Ethernet interface, and a raw IP interface with nanosecond time stamps
+    file.extend(block(1, &[1, 0, 0, 0, 0xff, 0xff, 0, 0]));
+    file.extend(block(1, &[101, 0, 0, 0, 0xff, 0xff, 0, 0, 9, 0, 1, 0, 9, 0, 0, 0, 0, 0, 0, 0]));
+    file.extend(epb(0, 1_460_408_319_000_001, &[1; 14]));
+    file.extend(epb(1, 1_460_408_320_000_002_000, &[0x45, 0, 0, 20]));
+    // An interface statistics block, which is skipped
+    file.extend(block(5, &[0; 12]));
+    file.extend(epb(0, 1_460_408_321_000_003, &[2; 14]));
+
+    let packets = OfflineNg::new(&file[..]).unwrap().collect::<Result<Vec<_>, _>>().unwrap();
+    let linktypes = packets.iter().map(|&(linktype, _)| linktype).collect::<Vec<_>>();
+    assert_eq!(linktypes, vec![Linktype::ETHERNET, Linktype::RAW, Linktype::ETHERNET]);
+    assert_eq!(packets[1].1.data, vec![0x45, 0, 0, 20]);
+    assert_eq!(packets[0].1.header.ts.tv_sec, 1_460_408_319);
+    assert_eq!(packets[0].1.header.ts.tv_usec, 1);
+    assert_eq!(packets[1].1.header.ts.tv_sec, 1_460_408_320);
+    assert_eq!(packets[1].1.header.ts.tv_usec, 2);
+
+    // Packets referring to an interface which was not described are rejected
+    let mut file = file[..28].to_vec();
+    file.extend(epb(0, 0, &[0; 14]));
+    assert!(OfflineNg::new(&file[..]).unwrap().next().unwrap().is_err());
+
+    let mut packets = OfflineNg::open("tests/data/packet_snaplen_65535.pcapng").unwrap();
+    let (linktype, packet) = packets.next().unwrap().unwrap();
+    assert_eq!(linktype, Linktype::ETHERNET);
+    assert_eq!(packet.header.len, 98);
+    assert!(packets.next().is_none());
+
+    assert!(OfflineNg::open("tests/data/packet_snaplen_65535.pcap").is_err());
+}
+
+#[test]
+fn test_packet_header_truncated() {
+    let header = header(20, 98);
+    assert!(header.is_truncated());
+    assert_eq!(header.truncated_bytes(), 78);
+
+    let header = PacketHeader { caplen: 98, ..header };
+    assert!(!header.is_truncated());
+    assert_eq!(header.truncated_bytes(), 0);
+
+    let mut cap = capture_from_test_file("packet_snaplen_20.pcap");
+    let packet = cap.next().unwrap();
+    assert!(packet.header.is_truncated());
+    assert_eq!(packet.header.truncated_bytes(), packet.header.len - 20);
+}
+
+#[test]
+fn test_skip() {
+    let mut packets = Packets::new();
+    for i in 0..5 {
+        packets.push(1460408319 + i, 1000, 1, 1, &[i as u8]);
+    }
+    let (_dir, tmpfile) = write_savefile(Linktype(1), &packets);
+
+    let mut cap = Capture::from_file(&tmpfile).unwrap();
+    assert_eq!(cap.skip(3).unwrap(), 3);
+    assert_eq!(cap.next().unwrap().data, &[3]);
+    assert_eq!(cap.skip(0).unwrap(), 0);
+    assert_eq!(cap.skip(10).unwrap(), 1);
+    assert_eq!(cap.next().err().unwrap(), Error::NoMorePackets);
+}
+
+#[test]
+fn test_error_into_io_error() {
+    let err: std::io::Error = Error::PcapError("bad filter".to_string()).into();
+    assert_eq!(err.kind(), std::io::ErrorKind::Other);
+    assert_eq!(err.to_string(), Error::PcapError("bad filter".to_string()).to_string());
+
+    let err: std::io::Error = Error::IoError(std::io::ErrorKind::NotFound).into();
+    assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+}
+
+#[test]
+#[cfg(any(target_os = "macos", target_os = "freebsd", target_os = "openbsd",
+          target_os = "dragonfly"))]
+#[ignore] // requires privileges to capture on the loopback device
+fn test_bsd_set_immediate() {
+    let mut cap = Capture::from_device("lo0").unwrap().open().unwrap();
+    cap.bsd_set_immediate(true).unwrap();
+    cap.bsd_set_immediate(false).unwrap();
+}
+
+#[test]
+fn test_packet_checksums() {
+    let header = header(0, 0);
+    let check = |data: &[u8]| {
+        let packet = Packet::new(&header, data);
+        (packet.ipv4_checksum_ok(Linktype::RAW),
+         packet.tcp_checksum_ok(Linktype::RAW),
+         packet.udp_checksum_ok(Linktype::RAW))
+    };
+
+    // IPv4/UDP from 192.168.0.1:1234 to 192.168.0.2:53
+    let udp4 = [
+        0x45, 0x00, 0x00, 0x21, 0x00, 0x01, 0x40, 0x00, 0x40, 0x11, 0xb9, 0x77,
+        0xc0, 0xa8, 0x00, 0x01, 0xc0, 0xa8, 0x00, 0x02, 0x04, 0xd2, 0x00, 0x35,
+        0x00, 0x0d, 0x35, 0xa7, 0x68, 0x65, 0x6c, 0x6c, 0x6f,
+    ];
+    assert_eq!(check(&udp4), (Some(true), None, Some(true)));
+    // Ethernet padding after the datagram is ignored
+    let mut padded = udp4.to_vec();
+    padded.extend_from_slice(&[0; 13]);
+    assert_eq!(check(&padded), (Some(true), None, Some(true)));
+    // A corrupted IPv4 header, and a corrupted payload
+    let mut corrupted = udp4;
+    corrupted[8] = 63;
+    assert_eq!(check(&corrupted), (Some(false), None, Some(true)));
+    let mut corrupted = udp4;
+    corrupted[32] = b'O';
+    assert_eq!(check(&corrupted), (Some(true), None, Some(false)));
+    // No UDP checksum
+    let mut unchecked = udp4;
+    unchecked[26] = 0;
+    unchecked[27] = 0;
+    assert_eq!(check(&unchecked), (Some(true), None, Some(true)));
+    // Truncated
+    assert_eq!(check(&udp4[..30]), (Some(true), None, None));
+    // An IHL below the minimum of 5 words
+    let mut malformed = udp4;
+    malformed[0] = 0x40;
+    assert_eq!(check(&malformed), (None, None, None));
+    assert_eq!(Packet::new(&header, &malformed).dns(Linktype::RAW), None);
+
+    // IPv4/TCP SYN from 192.168.0.1:40000 to 192.168.0.2:80
+    let tcp4 = [
+        0x45, 0x00, 0x00, 0x28, 0x00, 0x02, 0x40, 0x00, 0x40, 0x06, 0xb9, 0x7a,
+        0xc0, 0xa8, 0x00, 0x01, 0xc0, 0xa8, 0x00, 0x02, 0x9c, 0x40, 0x00, 0x50,
+        0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x50, 0x02, 0xff, 0xff,
+        0x91, 0xfd, 0x00, 0x00,
+    ];
+    assert_eq!(check(&tcp4), (Some(true), Some(true), None));
+    let mut corrupted = tcp4;
+    corrupted[38] = 1;
+    assert_eq!(check(&corrupted), (Some(true), Some(false), None));
+
+    // IPv6/UDP from [fe80::1]:1234 to [fe80::2]:53
+    let udp6 = [
+        0x60, 0x00, 0x00, 0x00, 0x00, 0x0d, 0x11, 0x40, 0xfe, 0x80, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+        0xfe, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x02, 0x04, 0xd2, 0x00, 0x35, 0x00, 0x0d, 0xb9, 0xf6,
+        0x68, 0x65, 0x6c, 0x6c, 0x6f,
+    ];
+    assert_eq!(check(&udp6), (None, None, Some(true)));
+    let mut corrupted = udp6;
+    corrupted[23] = 3;
+    assert_eq!(check(&corrupted), (None, None, Some(false)));
+
+    // The ICMP packet in the test file has a correct IPv4 header checksum
+    let mut cap = capture_from_test_file("packet_snaplen_65535.pcap");
+    let packet = cap.next().unwrap();
+    assert_eq!(packet.ipv4_checksum_ok(Linktype::ETHERNET), Some(true));
+    assert_eq!(packet.tcp_checksum_ok(Linktype::ETHERNET), None);
+}
+
+#[test]
+fn test_device_from_name_raw() {
+    use pcap::Device;
+
+    let device = Device::from_name_raw("netmap:eth0");
+    assert_eq!(device.name, "netmap:eth0");
+    assert_eq!(device, "netmap:eth0".into());
+    // pcap_create accepts any name; whether the backend is supported is only checked on
+    // activation
+    assert!(Capture::from_device(device).is_ok());
+}
+
+#[test]
+fn test_check_monotonic() {
+    let dir = TempDir::new("pcap").unwrap();
+    let write = |name: &str, packets: &Packets| {
+        let path = dir.path().join(name);
+        let mut save = Capture::dead(Linktype(1)).unwrap().savefile(&path).unwrap();
+        packets.foreach(|p| save.write(p));
+        path
+    };
+
+    let mut ordered = Packets::new();
+    ordered.push(1460408319, 1234, 1, 1, &[1]);
+    ordered.push(1460408319, 1234, 1, 1, &[2]);
+    ordered.push(1460408320, 0, 1, 1, &[3]);
+    let mut cap = Capture::from_file(write("ordered.pcap", &ordered)).unwrap();
+    assert!(cap.check_monotonic().unwrap());
+    ordered.verify(&mut cap);
+
+    let mut reordered = Packets::new();
+    reordered.push(1460408319, 1234, 1, 1, &[1]);
+    reordered.push(1460408319, 1000, 1, 1, &[2]);
+    reordered.push(1460408320, 0, 1, 1, &[3]);
+    let mut cap = Capture::from_file(write("reordered.pcap", &reordered)).unwrap();
+    assert!(!cap.check_monotonic().unwrap());
+    reordered.verify(&mut cap);
+}
+
+#[test]
+fn test_linktype_category() {
+    use pcap::LinktypeCategory;
+
+    assert_eq!(Linktype::IEEE802_11_RADIOTAP.category(), LinktypeCategory::WiFi);
+    assert_eq!(Linktype::USER0.category(), LinktypeCategory::User);
+    assert_eq!(Linktype::USER15.category(), LinktypeCategory::User);
+    assert_eq!(Linktype::ETHERNET.category(), LinktypeCategory::Ethernet);
+    assert_eq!(Linktype::BLUETOOTH_LE_LL.category(), LinktypeCategory::Bluetooth);
+    assert_eq!(Linktype::IEEE802_15_4_NOFCS.category(), LinktypeCategory::Ieee802154);
+    assert_eq!(Linktype::USBPCAP.category(), LinktypeCategory::Usb);
+    assert_eq!(Linktype::CAN_SOCKETCAN.category(), LinktypeCategory::Can);
+    assert_eq!(Linktype::GPRS_LLC.category(), LinktypeCategory::Cellular);
+    // Generic Framing Procedure is used over SONET/SDH and OTN rather than cellular networks
+    assert_eq!(Linktype::GPF_T.category(), LinktypeCategory::Other);
+    assert_eq!(Linktype::RAW.category(), LinktypeCategory::Other);
+    assert_eq!(Linktype(12345).category(), LinktypeCategory::Other);
+}
+
+#[test]
+fn test_ethernet_frame_builder() {
+    use pcap::frame::{EthernetFrameBuilder, ETHERNET_MIN_LEN};
+
+    let dst = [0xff; 6];
+    let src = [0x02, 0x00, 0x00, 0x00, 0x00, 0x01];
+    let frame = EthernetFrameBuilder::new(dst, src, 0x0800).payload(&[0x45, 0, 0, 20][..]).build();
+    assert_eq!(frame.len(), ETHERNET_MIN_LEN);
+    assert_eq!(frame[..6], dst);
+    assert_eq!(frame[6..12], src);
+    assert_eq!(frame[12..14], [0x08, 0x00]);
+    assert_eq!(frame[14..18], [0x45, 0, 0, 20]);
+    assert!(frame[18..].iter().all(|&b| b == 0));
+
+    let frame = EthernetFrameBuilder::new(dst, src, 0x88b5)
+        .payload(b"hello".to_vec())
+        .pad(false)
+        .build();
+    assert_eq!(frame.len(), 19);
+    assert_eq!(&frame[14..], b"hello");
+
+    let payload = vec![0xaa; 100];
+    let frame = EthernetFrameBuilder::new(dst, src, 0x86dd).payload(payload.clone()).build();
+    assert_eq!(frame.len(), 114);
+    assert_eq!(frame[14..], payload[..]);
+
+    // The frames can be parsed back
+    let header = header(frame.len() as u32, frame.len() as u32);
+    assert_eq!(Packet::new(&header, &frame).ethertype(Linktype::ETHERNET), Some(0x86dd));
+}
+
+#[test]
+fn test_packet_linux_sll2() {
+    use pcap::Direction;
+
+    let header = header(0, 0);
+    // LINUX_SLL2 header: protocol, reserved, interface index, ARPHRD type, packet type,
+    // address length, address
+    let mut data = vec![0x86, 0xdd, 0, 0, 0, 0, 0, 3, 0, 1, 4, 6, 1, 2, 3, 4, 5, 6, 0, 0];
+    data.extend_from_slice(&[0x60, 0, 0, 0]);
+
+    let packet = Packet::new(&header, &data);
+    assert_eq!(packet.interface_index(Linktype::LINUX_SLL2), Some(3));
+    assert_eq!(packet.direction(Linktype::LINUX_SLL2), Some(Direction::Out));
+    assert_eq!(packet.network_layer(Linktype::LINUX_SLL2), Some((0x86dd, &[0x60, 0, 0, 0][..])));
+    assert_eq!(packet.payload(Linktype::LINUX_SLL2), Some(&[0x60, 0, 0, 0][..]));
+    assert_eq!(packet.interface_index(Linktype::LINUX_SLL), None);
+
+    data[10] = 0; // to us
+    let packet = Packet::new(&header, &data);
+    assert_eq!(packet.direction(Linktype::LINUX_SLL2), Some(Direction::In));
+
+    // Truncated
+    let packet = Packet::new(&header, &data[..6]);
+    assert_eq!(packet.interface_index(Linktype::LINUX_SLL2), None);
+    assert_eq!(packet.direction(Linktype::LINUX_SLL2), None);
+    assert_eq!(packet.network_layer(Linktype::LINUX_SLL2), None);
+}
+
+#[test]
+#[cfg(windows)]
+#[ignore] // requires an adapter which may not support promiscuous mode, e.g. a Wi-Fi adapter
+fn test_open_with_warning_promisc() {
+    use pcap::{Device, Warning};
+
+    let device = Device::lookup().unwrap();
+    let (mut cap, warnings) = Capture::from_device(device)
+        .unwrap()
+        .promisc(true)
+        .timeout(10)
+        .open_with_warning()
+        .unwrap();
+    for warning in &warnings {
+        assert!(warning.code == Warning::PROMISC_NOT_SUPPORTED || warning.code == Warning::GENERIC,
+                "{}", warning);
+    }
+    // The capture remains usable despite the warning
+    cap.stats().unwrap();
+    match cap.next() {
+        Ok(_) | Err(pcap::Error::TimeoutExpired) => {}
+        Err(e) => panic!("{:?}", e),
+    }
+
+    // open() accepts the same warnings
+    let device = Device::lookup().unwrap();
+    Capture::from_device(device).unwrap().promisc(true).timeout(10).open().unwrap();
+}
+
+#[test]
+fn test_set_datalink_recompiles_filter() {
+    use pcap::BpfInstruction;
+
+    let mut cap = capture_from_test_file("packet_snaplen_65535.pcap");
+    assert_eq!(cap.get_filter(), None);
+    cap.filter("icmp").unwrap();
+    assert_eq!(cap.get_filter(), Some("icmp"));
+
+    // Savefiles only support their own datalink type, but the filter is still recompiled
+    cap.set_datalink(Linktype::ETHERNET).unwrap();
+    assert_eq!(cap.get_filter(), Some("icmp"));
+    assert!(cap.next().is_ok());
+
+    // The filter is kept when the datalink type cannot be changed
+    assert!(cap.set_datalink(Linktype::RAW).is_err());
+    assert_eq!(cap.get_datalink(), Linktype::ETHERNET);
+    assert_eq!(cap.get_filter(), Some("icmp"));
+
+    // Raw programs cannot be recompiled, so they are not recorded
+    cap.set_filter_raw(&[BpfInstruction { code: 0x06, jt: 0, jf: 0, k: 0xffff }]).unwrap();
+    assert_eq!(cap.get_filter(), None);
+}
+
+#[test]
+fn test_packet_arp() {
+    use pcap::{ArpInfo, ArpOperation};
+
+    let header = header(0, 0);
+    let mac1 = [0x02, 0, 0, 0, 0, 1];
+    let mac2 = [0x02, 0, 0, 0, 0, 2];
+    let arp = |dst: &[u8], operation: u8, sender: (&[u8], [u8; 4]), target: (&[u8], [u8; 4])| {
+        let mut frame = dst.to_vec();
+        frame.extend_from_slice(sender.0);
+        frame.extend_from_slice(&[0x08, 0x06, 0, 1, 0x08, 0x00, 6, 4, 0, operation]);
+        frame.extend_from_slice(sender.0);
+        frame.extend_from_slice(&sender.1);
+        frame.extend_from_slice(target.0);
+        frame.extend_from_slice(&target.1);
+        // Ethernet padding
+        frame.resize(60, 0);
+        frame
+    };
+
+    // Who has 192.168.0.2? Tell 192.168.0.1
+    let request = arp(&[0xff; 6], 1, (&mac1, [192, 168, 0, 1]), (&[0; 6], [192, 168, 0, 2]));
+    assert_eq!(Packet::new(&header, &request).arp(Linktype::ETHERNET), Some(ArpInfo {
+        hardware_type: 1,
+        protocol_type: 0x0800,
+        operation: ArpOperation::Request,
+        sender_hardware_addr: &mac1,
+        sender_protocol_addr: &[192, 168, 0, 1],
+        target_hardware_addr: &[0; 6],
+        target_protocol_addr: &[192, 168, 0, 2],
+    }));
+
+    // 192.168.0.2 is at 02:00:00:00:00:02
+    let reply = arp(&mac1, 2, (&mac2, [192, 168, 0, 2]), (&mac1, [192, 168, 0, 1]));
+    let info = Packet::new(&header, &reply).arp(Linktype::ETHERNET).unwrap();
+    assert_eq!(info.operation, ArpOperation::Reply);
+    assert_eq!(info.sender_hardware_addr, mac2);
+    assert_eq!(info.sender_protocol_addr, [192, 168, 0, 2]);
+    assert_eq!(info.target_hardware_addr, mac1);
+    assert_eq!(info.target_protocol_addr, [192, 168, 0, 1]);
+
+    // Truncated
+    assert_eq!(Packet::new(&header, &reply[..40]).arp(Linktype::ETHERNET), None);
+    // Not ARP
+    let mut cap = capture_from_test_file("packet_snaplen_65535.pcap");
+    assert_eq!(cap.next().unwrap().arp(Linktype::ETHERNET), None);
+}
+
+#[test]
+fn test_packet_ring() {
+    use pcap::ring::PacketRing;
+
+    let mut packets = Packets::new();
+    for i in 0..10 {
+        packets.push(1460408319 + i, 0, 100, 100, &[i as u8; 100]);
+    }
+
+    let (_dir, tmpfile) = write_savefile(Linktype(1), &packets);
+
+    let mut cap = Capture::from_file(&tmpfile).unwrap();
+    let mut ring = PacketRing::new(4, 128);
+    assert!(ring.is_empty());
+    let mut buffers = vec![];
+    for i in 0..10 {
+        let packet = ring.read_next(&mut cap).unwrap();
+        assert_eq!(packet.data, [i as u8; 100]);
+        assert_eq!(packet.data.capacity(), 128);
+        buffers.push(packet.data.as_ptr());
+    }
+    assert!(ring.read_next(&mut cap).is_err());
+
+    // Every read after the first lap reused the buffer of the slot it overwrote
+    for i in 4..10 {
+        assert_eq!(buffers[i], buffers[i - 4]);
+    }
+    assert_eq!(ring.len(), 4);
+    let held: Vec<u8> = ring.iter().map(|p| p.data[0]).collect();
+    assert_eq!(held, [6, 7, 8, 9]);
+}
+
+#[test]
+fn test_from_file_with_linktype() {
+    // An IPv4 header, mislabeled as Ethernet
+    let ip = [0x45, 0, 0, 20, 0, 0, 0, 0, 64, 1, 0, 0, 127, 0, 0, 1, 127, 0, 0, 1];
+    let mut packets = Packets::new();
+    packets.push(1460408319, 0, ip.len() as u32, ip.len() as u32, &ip);
+
+    let (_dir, tmpfile) = write_savefile(Linktype::ETHERNET, &packets);
+
+    let mut cap = Capture::from_file_with_linktype(&tmpfile, Linktype::RAW).unwrap();
+    assert_eq!(cap.get_datalink(), Linktype::RAW);
+    let (packet, payload) = cap.next_parsed().unwrap();
+    assert_eq!(payload, Some(&ip[..]));
+    assert_eq!(packet.network_layer(Linktype::RAW), Some((0x0800, &ip[..])));
+
+    assert!(Capture::from_file_with_linktype(&tmpfile, Linktype(-5)).is_err());
+}
+
+#[test]
+fn test_error_classify() {
+    use pcap::{ActivationError, ErrorClass};
+
+    let classify = |msg: &str| pcap::Error::PcapError(msg.to_string()).classify();
+    assert_eq!(classify("eth9: No such device exists (SIOCGIFHWADDR: No such device)"),
+               ErrorClass::NoSuchDevice);
+    assert_eq!(classify("eth0: You don't have permission to capture on that device \
+                         (socket: Operation not permitted)"),
+               ErrorClass::PermissionDenied);
+    assert_eq!(classify("(cannot open BPF device) /dev/bpf0: Permission denied"),
+               ErrorClass::PermissionDenied);
+    assert_eq!(classify("can't open device: Device or resource busy"), ErrorClass::DeviceBusy);
+    assert_eq!(classify("syntax error in filter expression"), ErrorClass::Other);
+
+    let activation = pcap::Error::Activation {
+        kind: ActivationError::PromiscPermissionDenied,
+        message: String::new(),
+    };
+    assert_eq!(activation.classify(), ErrorClass::PermissionDenied);
+    assert_eq!(pcap::Error::NoMorePackets.classify(), ErrorClass::Other);
+}
+
+#[test]
+fn test_next_ex() {
+    use pcap::NextResult;
+
+    let mut cap = capture_from_test_file("packet_snaplen_65535.pcap");
+    match cap.next_ex().unwrap() {
+        NextResult::Packet(packet) => assert_eq!(packet.header.caplen, 98),
+        result => panic!("unexpected {:?}", result),
+    }
+    assert_eq!(cap.next_ex().unwrap(), NextResult::Eof);
+}
+
+#[test]
+#[ignore] // requires privileges to capture on the loopback device
+fn test_next_ex_timeout() {
+    use pcap::NextResult;
+
+    let mut cap = Capture::from_device("lo").unwrap().timeout(10).open().unwrap();
+    // Nothing but broadcasts could match, and there are none on the loopback device
+    cap.filter("ether broadcast").unwrap();
+    assert_eq!(cap.next_ex().unwrap(), NextResult::Timeout);
+}
+
+#[test]
+#[cfg(not(windows))]
+fn test_pcap_writer() {
+    use pcap::writer::PcapWriter;
+
+    let mut packets = Packets::new();
+    packets.push(1460408319, 1234, 1, 1, &[1]);
+    packets.push(1460408320, 4321, 2, 3, &[2, 3]);
+
+    let mut writer = PcapWriter::new(vec![], Linktype::ETHERNET).unwrap();
+    packets.foreach(|p| writer.write(p).unwrap());
+    let bytes = writer.into_inner();
+
+    let mut cap = Capture::from_bytes(&bytes).unwrap();
+    assert_eq!(cap.get_datalink(), Linktype::ETHERNET);
+    assert_eq!(cap.get_snaplen(), 65535);
+    packets.verify(&mut cap);
+}
+
+#[test]
+fn test_packet_dns() {
+    use pcap::frame::EthernetFrameBuilder;
+    use pcap::{DnsInfo, DnsQuestion};
+
+    let ipv4 = |protocol: u8, segment: &[u8]| {
+        let total_len = (20 + segment.len()) as u16;
+        let mut packet = vec![0x45, 0];
+        packet.extend_from_slice(&total_len.to_be_bytes());
+        packet.extend_from_slice(&[0, 0, 0, 0, 64, protocol, 0, 0, 10, 0, 0, 1, 10, 0, 0, 2]);
+        packet.extend_from_slice(segment);
+        EthernetFrameBuilder::new([2, 0, 0, 0, 0, 2], [2, 0, 0, 0, 0, 1], 0x0800)
+            .payload(packet)
+            .build()
+    };
+    let header = header(0, 0);
+
+    // A query for the A record of example.com and, compressed, www.example.com
+    let mut query = vec![0x12, 0x34, 0x01, 0x00, 0, 2, 0, 0, 0, 0, 0, 0];
+    query.extend_from_slice(b"\x07example\x03com\x00\x00\x01\x00\x01");
+    query.extend_from_slice(b"\x03www\xc0\x0c\x00\x1c\x00\x01");
+    let expected = DnsInfo {
+        id: 0x1234,
+        is_response: false,
+        opcode: 0,
+        rcode: 0,
+        questions: vec![
+            DnsQuestion { name: "example.com".to_string(), qtype: 1, qclass: 1 },
+            DnsQuestion { name: "www.example.com".to_string(), qtype: 28, qclass: 1 },
+        ],
+    };
+
+    let mut udp = vec![0xc3, 0x50, 0, 53];
+    udp.extend_from_slice(&((8 + query.len()) as u16).to_be_bytes());
+    udp.extend_from_slice(&[0, 0]);
+    udp.extend_from_slice(&query);
+    let frame = ipv4(17, &udp);
+    assert_eq!(Packet::new(&header, &frame).dns(Linktype::ETHERNET), Some(expected.clone()));
+
+    let mut tcp = vec![0xc3, 0x50, 0, 53, 0, 0, 0, 1, 0, 0, 0, 0];
+    tcp.extend_from_slice(&[0x50, 0x18, 0xff, 0xff, 0, 0, 0, 0]);
+    tcp.extend_from_slice(&(query.len() as u16).to_be_bytes());
+    tcp.extend_from_slice(&query);
+    let frame = ipv4(6, &tcp);
+    assert_eq!(Packet::new(&header, &frame).dns(Linktype::ETHERNET), Some(expected));
+
+    // Truncated question section
+    let mut truncated = udp.clone();
+    truncated.truncate(udp.len() - 3);
+    let udp_len = truncated.len() as u16;
+    truncated[4..6].copy_from_slice(&udp_len.to_be_bytes());
+    let frame = ipv4(17, &truncated);
+    assert_eq!(Packet::new(&header, &frame).dns(Linktype::ETHERNET), None);
+
+    // Not DNS
+    udp[2..4].copy_from_slice(&[0x1f, 0x90]);
+    let frame = ipv4(17, &udp);
+    assert_eq!(Packet::new(&header, &frame).dns(Linktype::ETHERNET), None);
+    let mut cap = capture_from_test_file("packet_snaplen_65535.pcap");
+    assert_eq!(cap.next().unwrap().dns(Linktype::ETHERNET), None);
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+#[ignore] // requires privileges to capture on the loopback device
+fn test_filter_in_kernel() {
+    let mut cap = Capture::from_device("lo").unwrap().timeout(10).open().unwrap();
+    assert_eq!(cap.filter_in_kernel(), Some(false));
+    cap.filter("icmp").unwrap();
+    assert_eq!(cap.filter_in_kernel(), Some(true));
+}
+
+#[test]
+fn test_open_or_return() {
+    let cap = Capture::from_device("pcap-test-missing0").unwrap();
+    // Unprivileged users may get PermissionDenied rather than NoSuchDevice, so only the
+    // failure itself is checked
+    let (cap, _) = match cap.open_or_return() {
+        Err(failed) => failed,
+        Ok(_) => panic!("opened a device which does not exist"),
+    };
+
+    // The returned capture can still be configured and activated again
+    let cap = cap.promisc(false).snaplen(128);
+    assert!(cap.open_or_return().is_err());
+}
+
+#[test]
+fn test_packet_tcp() {
+    use pcap::frame::EthernetFrameBuilder;
+    use pcap::{TcpFlags, TcpInfo};
+
+    let header = header(0, 0);
+    let frame = |options: &[u8], segment: &[u8]| {
+        let total_len = (20 + options.len() + segment.len()) as u16;
+        let mut packet = vec![0x45 + options.len() as u8 / 4, 0];
+        packet.extend_from_slice(&total_len.to_be_bytes());
+        packet.extend_from_slice(&[0, 0, 0x40, 0, 64, 6, 0, 0, 10, 0, 0, 1, 10, 0, 0, 2]);
+        packet.extend_from_slice(options);
+        packet.extend_from_slice(segment);
+        EthernetFrameBuilder::new([2, 0, 0, 0, 0, 2], [2, 0, 0, 0, 0, 1], 0x0800)
+            .payload(packet)
+            .build()
+    };
+
+    // A SYN from port 50000 to 80, in an IPv4 packet with a router alert option
+    let syn = [0xc3, 0x50, 0, 80, 0, 0, 0x10, 0, 0, 0, 0, 0, 0x50, 0x02, 0xfa, 0xf0, 0, 0, 0, 0];
+    let syn = frame(&[0x94, 0x04, 0, 0], &syn);
+    assert_eq!(Packet::new(&header, &syn).tcp(Linktype::ETHERNET), Some(TcpInfo {
+        src_port: 50000,
+        dst_port: 80,
+        seq: 0x1000,
+        ack: 0,
+        flags: TcpFlags::SYN,
+        window: 0xfaf0,
+    }));
+
+    let syn_ack = [0, 80, 0xc3, 0x50, 0, 0, 0x20, 0, 0, 0, 0x10, 1,
+                   0x50, 0x12, 0x72, 0x10, 0, 0, 0, 0];
+    let syn_ack = frame(&[], &syn_ack);
+    let info = Packet::new(&header, &syn_ack).tcp(Linktype::ETHERNET).unwrap();
+    assert_eq!((info.src_port, info.dst_port), (80, 50000));
+    assert_eq!((info.seq, info.ack), (0x2000, 0x1001));
+    assert_eq!(info.flags, TcpFlags::SYN | TcpFlags::ACK);
+    assert_eq!(info.window, 0x7210);
+
+    // Truncated
+    assert_eq!(Packet::new(&header, &syn_ack[..44]).tcp(Linktype::ETHERNET), None);
+    // Not TCP
+    let mut cap = capture_from_test_file("packet_snaplen_65535.pcap");
+    assert_eq!(cap.next().unwrap().tcp(Linktype::ETHERNET), None);
+}
+
+#[test]
+fn test_with_datalink() {
+    let path = Path::new("tests/data/packet_snaplen_65535.pcap");
+    let mut cap = Capture::from_file_with_linktype(path, Linktype::RAW).unwrap();
+    assert_eq!(cap.get_datalink(), Linktype::RAW);
+    {
+        let mut guard = cap.with_datalink(Linktype::ETHERNET).unwrap();
+        assert_eq!(guard.get_datalink(), Linktype::ETHERNET);
+        let (_, payload) = guard.next_parsed().unwrap();
+        assert_eq!(payload.map(|p| p[0]), Some(0x45));
+    }
+    assert_eq!(cap.get_datalink(), Linktype::RAW);
+
+    // Savefiles only support their own datalink type, so the guard is not created
+    assert!(cap.with_datalink(Linktype::IEEE802_11).is_err());
+    assert_eq!(cap.get_datalink(), Linktype::RAW);
+}
+
+#[test]
+fn test_count_until() {
+    use std::cell::Cell;
+
+    let mut packets = Packets::new();
+    for i in 0..10 {
+        packets.push(1460408319 + i, 0, i as u32 + 1, 100, &vec![0; i as usize + 1]);
+    }
+
+    let (_dir, tmpfile) = write_savefile(Linktype(1), &packets);
+
+    let mut cap = Capture::from_file(&tmpfile).unwrap();
+    assert_eq!(cap.count_until(|| false).unwrap(), (10, 55));
+
+    // Stop after 3 packets
+    let mut cap = Capture::from_file(&tmpfile).unwrap();
+    let calls = Cell::new(0);
+    let stop = || {
+        calls.set(calls.get() + 1);
+        calls.get() > 3
+    };
+    assert_eq!(cap.count_until(stop).unwrap(), (3, 6));
+}
+
+#[test]
+fn test_device_display_name() {
+    use pcap::{Device, DeviceFlags};
+
+    let device = |name: &str, desc: Option<&str>, flags| Device {
+        name: name.to_string(),
+        desc: desc.map(str::to_string),
+        flags,
+    };
+    let desc = "Intel(R) Ethernet Connection I219-LM";
+    let windows = device(r"\Device\NPF_{00000000-0000-0000-0000-000000000000}", Some(desc),
+                         DeviceFlags::UP);
+    assert_eq!(windows.display_name(), desc);
+
+    assert_eq!(device("lo", None, DeviceFlags::LOOPBACK).display_name(), "Loopback (lo)");
+    assert_eq!(device("eth0", None, DeviceFlags::UP).display_name(), "Ethernet (eth0)");
+    assert_eq!(device("enp3s0", Some(""), DeviceFlags::UP).display_name(), "Ethernet (enp3s0)");
+    assert_eq!(device("wlp2s0", None, DeviceFlags::UP).display_name(), "Wi-Fi (wlp2s0)");
+    assert_eq!(device("mon0", None, DeviceFlags::WIRELESS).display_name(), "Wi-Fi (mon0)");
+    assert_eq!(device("veth1a2b3c", None, DeviceFlags::empty()).display_name(),
+               "Virtual Ethernet (veth1a2b3c)");
+    assert_eq!(device("xyz0", None, DeviceFlags::empty()).display_name(), "xyz0");
+}
+
+#[test]
+fn test_stat_display() {
+    use pcap::Stat;
+
+    let stat = Stat { received: 1000, dropped: 5, if_dropped: 0 };
+    assert_eq!(stat.to_string(), "recv=1000 drop=5 ifdrop=0");
+    assert!((stat.drop_rate() - 0.005).abs() < 1e-12);
+
+    let stat = Stat { received: 0, dropped: 3, if_dropped: 0 };
+    assert!((stat.drop_rate() - 3.0).abs() < 1e-12);
+    let stat = Stat { received: 0, dropped: 0, if_dropped: 0 };
+    assert_eq!(stat.drop_rate(), 0.0);
+}
+
+#[test]
+fn test_min_to_copy() {
+    let cap = capture_from_test_file("packet_snaplen_65535.pcap");
+    // On Windows the call reaches pcap_setmintocopy, which refuses savefiles
+    #[cfg(windows)]
+    match cap.min_to_copy(64 * 1024) {
+        Err(Error::PcapError(_)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+    #[cfg(not(windows))]
+    assert_eq!(cap.min_to_copy(64 * 1024), Err(Error::Unsupported));
+}
+
+#[test]
+#[cfg(windows)]
+#[ignore] // requires privileges to capture on the default device
+fn test_min_to_copy_live() {
+    let cap = Capture::from_device(pcap::Device::lookup().unwrap())
+        .unwrap()
+        .open()
+        .unwrap();
+    assert!(cap.min_to_copy(64 * 1024).is_ok());
+    assert!(cap.min_to_copy(0).is_ok());
+}